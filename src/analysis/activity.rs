@@ -0,0 +1,247 @@
+use crate::analysis::{limit, share, Analysis, AnalysisOptions, OptionSpec, OptionValue, GRAPH_WIDTH, MIN_CALL_DURATION};
+use crate::data::{dataset_sum, CalendarData, Graph, Unit};
+use crate::render::png;
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{AuthorIdx, DirectMessages};
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, Timelike};
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+pub struct TextingFrequency;
+
+impl Analysis for TextingFrequency {
+    fn id(&self) -> &'static str {
+        "texting-frequency"
+    }
+
+    fn description(&self) -> &'static str {
+        "Texting Frequency (Lifetime Graph; Weekly Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Texting Frequency (Lifetime Graph; Weekly Buckets)");
+
+        let Some(earliest_message_day) = dms.texts().map(|text| dms.local_date(text)).min() else { return Ok(report) };
+        let earliest_message_date = NaiveDate::from_yo_opt(earliest_message_day.year(), earliest_message_day.ordinal0() / 7 * 7 + 1).unwrap();
+
+        let mut graph = Graph::new(dms.authors.labels(), 0, |idx| earliest_message_date.checked_add_days(Days::new(idx as u64 * 7)).unwrap().format("Week of %b %d, %Y").to_string(), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+
+        for text in dms.texts() {
+            let date = dms.local_date(text);
+            let delta = date - earliest_message_date;
+            let idx = delta.num_days() as usize / 7;
+            graph.add(text.author.index(), idx, 1);
+        }
+
+        report.push(graph.finish("Texting Frequency"));
+
+        Ok(report)
+    }
+}
+
+pub struct TextTimeOfDay;
+
+impl Analysis for TextTimeOfDay {
+    fn id(&self) -> &'static str {
+        "text-time-of-day"
+    }
+
+    fn description(&self) -> &'static str {
+        "Text Frequency (Time of Day Graph)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Text Time of Day Graph (10m groupings)");
+
+        let mut graph = Graph::new(dms.authors.labels(), 5 * 6 + 3, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 6, minutes = (idx % 6) * 10), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+
+        for text in dms.texts() {
+            let time = dms.local_time(text).time();
+            let index = (time.hour() * 6 + time.minute() / 10) as usize;
+            graph.add(text.author.index(), index, 1);
+        }
+        graph.add(0, 24 * 6 - 1, 0);
+
+        report.push(graph.finish("Text Time of Day"));
+
+        Ok(report)
+    }
+}
+
+// runs of consecutive days, as inclusive (first, last) pairs
+fn streaks(days: &BTreeSet<NaiveDate>) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut streaks = Vec::<(NaiveDate, NaiveDate)>::new();
+    for day in days.iter().copied() {
+        match streaks.last_mut().filter(|(_, last)| last.succ_opt() == Some(day)) {
+            Some((_, last)) => *last = day,
+            None => streaks.push((day, day)),
+        }
+    }
+    streaks
+}
+
+fn streak_length((first, last): (NaiveDate, NaiveDate)) -> usize {
+    (last - first).num_days() as usize + 1
+}
+
+pub struct Streaks;
+
+impl Analysis for Streaks {
+    fn id(&self) -> &'static str {
+        "streaks"
+    }
+
+    fn description(&self) -> &'static str {
+        "Messaging Streaks and Droughts"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(10)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Messaging Streaks and Droughts (days with a message or call)");
+
+        let active_days = |author: Option<AuthorIdx>| dms.texts().filter(|text| author.is_none_or(|author| text.author == author)).map(|text| dms.local_date(text)).chain(dms.calls().filter(|call| author.is_none_or(|author| call.author == author)).map(|call| dms.local_date(call))).collect::<BTreeSet<_>>();
+        let days = active_days(None);
+        let mut all_streaks = streaks(&days);
+        let Some(last) = all_streaks.last().copied() else { return Ok(report) };
+        // the last day may not be over yet, so a streak up to the day before it is still going
+        let last_day = dms.last_day();
+        let still_going = |(_, until): (NaiveDate, NaiveDate)| until.succ_opt().expect("Valid date") >= last_day;
+        let current = Some(last).filter(|streak| still_going(*streak));
+        let longest = all_streaks.iter().copied().max_by_key(|streak| (streak_length(*streak), std::cmp::Reverse(streak.0))).expect("There is at least one streak");
+        let drought = all_streaks.iter().tuple_windows().map(|((_, last), (next, _))| (last.succ_opt().expect("Valid date"), next.pred_opt().expect("Valid date"))).max_by_key(|drought| (streak_length(*drought), std::cmp::Reverse(drought.0)));
+
+        report.push(Section::Stats(vec![
+            ("active days".to_owned(), Cell::Integer(days.len())),
+            ("longest streak".to_owned(), Cell::Integer(streak_length(longest))),
+            ("longest streak from".to_owned(), Cell::Date(longest.0)),
+            ("longest streak until".to_owned(), Cell::Date(longest.1)),
+            ("current streak".to_owned(), Cell::Integer(current.map_or(0, streak_length))),
+            ("current streak from".to_owned(), current.map_or(Cell::Empty, |(first, _)| Cell::Date(first))),
+            ("last active day".to_owned(), Cell::Date(last.1)),
+            ("longest drought".to_owned(), drought.map_or(Cell::Integer(0), |drought| Cell::Integer(streak_length(drought)))),
+            ("longest drought from".to_owned(), drought.map_or(Cell::Empty, |(first, _)| Cell::Date(first))),
+            ("longest drought until".to_owned(), drought.map_or(Cell::Empty, |(_, last)| Cell::Date(last))),
+        ]));
+
+        report.push(Section::Heading("Per Author".to_owned()));
+        let mut table = Table::new("Streaks per Author", vec!["author", "active days", "longest streak", "from", "until", "current streak"]);
+        for (author, _) in dms.authors.iter() {
+            let author_streaks = streaks(&active_days(Some(author)));
+            let longest = author_streaks.iter().copied().max_by_key(|streak| (streak_length(*streak), std::cmp::Reverse(streak.0)));
+            let current = author_streaks.last().copied().filter(|streak| still_going(*streak));
+            table.push(vec![Cell::Text(dms.authors[author].label.clone()), Cell::Integer(author_streaks.iter().copied().map(streak_length).sum()), longest.map_or(Cell::Integer(0), |streak| Cell::Integer(streak_length(streak))), longest.map_or(Cell::Empty, |(first, _)| Cell::Date(first)), longest.map_or(Cell::Empty, |(_, last)| Cell::Date(last)), Cell::Integer(current.map_or(0, streak_length))]);
+        }
+        report.push(table);
+
+        report.push(Section::Heading("Active Days per Year".to_owned()));
+        let mut table = Table::new("Active Days per Year", vec!["year", "active days", "share of year"]);
+        for (year, days) in days.iter().chunk_by(|day| day.year()).into_iter() {
+            let active = days.count();
+            let year_length = NaiveDate::from_yo_opt(year, 366).map_or(365, |_| 366);
+            table.push(vec![Cell::Text(year.to_string()), Cell::Integer(active), share(active, year_length)]);
+        }
+        report.push(table);
+
+        all_streaks.sort_by_key(|streak| (std::cmp::Reverse(streak_length(*streak)), streak.0));
+        report.push(Section::Heading("Top Streaks".to_owned()));
+        let mut table = Table::ranked("Top Streaks", vec!["days", "from", "until"]);
+        for (first, last) in all_streaks.into_iter().take(options.integer("limit")) {
+            table.push(vec![Cell::Integer(streak_length((first, last))), Cell::Date(first), Cell::Date(last)]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct CalendarHeatmap;
+
+impl Analysis for CalendarHeatmap {
+    fn id(&self) -> &'static str {
+        "calendar-heatmap"
+    }
+
+    fn description(&self) -> &'static str {
+        "Calendar Heatmap (Weekday Rows; Weekly Columns)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[
+            OptionSpec { key: "call-minutes", description: "Shade days by time spent in calls instead of messages sent", default: OptionValue::Flag(false) },
+            MIN_CALL_DURATION,
+            OptionSpec { key: "truecolor", description: "Draw the text heatmap in 24-bit colour instead of the 256-colour palette", default: OptionValue::Flag(false) },
+            OptionSpec { key: "png", description: "Also write the heatmap to a PNG, one panel per year", default: OptionValue::Flag(false) },
+        ];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let call_minutes = options.flag("call-minutes");
+        let mut report = Report::new(self.id(), if call_minutes { "Calendar Heatmap (call time per day)" } else { "Calendar Heatmap (messages per day)" });
+
+        let mut days = BTreeMap::<NaiveDate, usize>::new();
+        if call_minutes {
+            for call in dms.calls_at_least(options.duration("min-call-duration").into()) {
+                *days.entry(dms.local_date(call)).or_default() += call.duration().num_milliseconds() as usize;
+            }
+        } else {
+            for text in dms.texts() {
+                *days.entry(dms.local_date(text)).or_default() += 1;
+            }
+        }
+
+        let calendar = CalendarData {
+            title: if call_minutes { "Call Time per Day".to_owned() } else { "Messages per Day".to_owned() },
+            days,
+            unit: if call_minutes { Unit::Milliseconds } else { Unit::Count },
+            truecolor: options.flag("truecolor"),
+        };
+
+        if options.flag("png") {
+            let path = PathBuf::from(format!("Calendar Heatmap - {channel_name} - {id}.png", channel_name = dms.channel.name, id = dms.channel.id));
+            png::write_calendar(&calendar, options.style(), &path)?;
+            report.push(Section::File(path));
+        }
+        report.push(calendar);
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    #[test]
+    fn streaks_of_consecutive_days() {
+        let days = [day(1, 1), day(1, 2), day(1, 3), day(1, 5), day(1, 31), day(2, 1)].into_iter().collect::<BTreeSet<_>>();
+        let streaks = streaks(&days);
+        assert_eq!(streaks, [(day(1, 1), day(1, 3)), (day(1, 5), day(1, 5)), (day(1, 31), day(2, 1))]);
+        assert_eq!(streaks.iter().copied().map(streak_length).collect::<Vec<_>>(), [3, 1, 2]);
+    }
+
+    #[test]
+    fn streaks_of_nothing() {
+        assert!(streaks(&BTreeSet::new()).is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use crate::analysis::{Analysis, AnalysisOptions};
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{DirectMessages, GROUP_AUTHOR_ID};
+use anyhow::Result;
+
+pub struct NameHistory;
+
+impl Analysis for NameHistory {
+    fn id(&self) -> &'static str {
+        "name-history"
+    }
+
+    fn description(&self) -> &'static str {
+        "Username and Nickname History"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Username and Nickname History");
+
+        for (idx, author) in dms.authors.iter().filter(|(_, author)| author.id != GROUP_AUTHOR_ID) {
+            report.push(Section::Heading(format!("{label} ({id})", label = author.label, id = author.id)));
+
+            let mut history = author.history.iter().collect::<Vec<_>>();
+            history.sort_by_key(|record| record.first_seen);
+
+            let mut table = Table::new(author.label.clone(), vec!["name", "nickname", "first_seen", "last_seen"]);
+            for record in history {
+                table.push(vec![Cell::Text(record.name.clone()), Cell::Text(record.nickname.clone()), Cell::Timestamp(dms.local(idx, record.first_seen)), Cell::Timestamp(dms.local(idx, record.last_seen))]);
+            }
+            report.push(table);
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,260 @@
+use crate::analysis::{limit, Analysis, AnalysisOptions, OptionSpec, GRAPH_WIDTH, MIN_CALL_DURATION};
+use crate::data::{dataset_average, dataset_sum, Graph, TimeQuantity};
+use crate::render::png;
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{Call, DirectMessages};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Weekday};
+use num_traits::FromPrimitive;
+use std::path::PathBuf;
+
+pub struct TopCallLengths;
+
+impl Analysis for TopCallLengths {
+    fn id(&self) -> &'static str {
+        "top-call-lengths"
+    }
+
+    fn description(&self) -> &'static str {
+        "Top Call Lengths"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(25)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let limit = options.integer("limit");
+        let mut report = Report::new(self.id(), format!("Top {limit} Call Lengths"));
+        let mut calls = dms.calls().collect::<Vec<_>>();
+
+        calls.sort_by_key(|call| call.duration());
+
+        report.push(Section::Stats(vec![
+            ("total calls".to_owned(), Cell::Integer(calls.len())),
+            ("8 hour calls".to_owned(), Cell::Integer(calls.iter().filter(|call| call.duration() >= TimeDelta::hours(8)).count())),
+        ]));
+
+        let mut table = Table::ranked("Top Call Lengths", vec!["length", "start_timestamp", "id"]);
+        for call in calls.into_iter().rev().take(limit) {
+            table.push(vec![Cell::Duration(TimeQuantity::from(call.duration())), Cell::Timestamp(dms.local_time(call)), Cell::Id(call.id)]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct TotalCallLengths;
+
+impl Analysis for TotalCallLengths {
+    fn id(&self) -> &'static str {
+        "total-call-lengths"
+    }
+
+    fn description(&self) -> &'static str {
+        "Total Call Lengths"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Total Call Lengths");
+        let len = TimeQuantity::from(dms.calls().map(Call::duration).sum::<TimeDelta>());
+
+        report.push(Section::Stats(vec![("total length".to_owned(), Cell::Duration(len))]));
+
+        Ok(report)
+    }
+}
+
+pub struct CallStartTimeOfDay;
+
+impl Analysis for CallStartTimeOfDay {
+    fn id(&self) -> &'static str {
+        "call-start-time-of-day"
+    }
+
+    fn description(&self) -> &'static str {
+        "Call Start Frequency (Time of Day Graph)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let min_duration = options.duration("min-call-duration");
+        let mut report = Report::new(self.id(), format!("Call Start Time of Day Graph (min = {min_duration}, 15m groupings)"));
+
+        let mut graph = Graph::new(dms.authors.labels(), 5 * 4 + 2, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 4, minutes = (idx % 4) * 15), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+
+        for call in dms.calls_at_least(min_duration.into()) {
+            let time = dms.local_time(call).time();
+            let index = (time.hour() * 4 + time.minute() / 15) as usize;
+            graph.add(call.author.index(), index, 1);
+        }
+        graph.add(0, 24 * 4 - 1, 0);
+
+        report.push(graph.finish("Call Start Time of Day"));
+
+        Ok(report)
+    }
+}
+
+pub struct CallDurationByMonth;
+
+impl Analysis for CallDurationByMonth {
+    fn id(&self) -> &'static str {
+        "call-duration-by-month"
+    }
+
+    fn description(&self) -> &'static str {
+        "Call Duration Graph (Annual Graph; Monthly Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let min_duration = options.duration("min-call-duration");
+        let mut report = Report::new(self.id(), format!("Call Duration by Month Graph (min = {min_duration})"));
+
+        let mut graph = Graph::new(vec![dms.channel.name.as_str()], 0, |idx| format!("{month}", month = NaiveDate::from_ymd_opt(1, (idx + 1) as u32, 1).expect("Valid date").format("%h")), dataset_average, options.integer("width"));
+
+        for call in dms.calls_at_least(min_duration.into()) {
+            let index = dms.local_date(call).month0() as usize;
+            graph.add(0, index, TimeQuantity::from(call.duration()));
+        }
+
+        report.push(graph.finish("Call Duration by Month"));
+
+        Ok(report)
+    }
+}
+
+pub struct CallDurationByDayOfWeek;
+
+impl Analysis for CallDurationByDayOfWeek {
+    fn id(&self) -> &'static str {
+        "call-duration-by-day-of-week"
+    }
+
+    fn description(&self) -> &'static str {
+        "Call Duration Graph (Weekly Graph; Daily Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let min_duration = options.duration("min-call-duration");
+        let mut report = Report::new(self.id(), format!("Call Duration by Day of Week Graph (min = {min_duration})"));
+
+        let mut graph = Graph::new(vec![dms.channel.name.as_str()], 0, |idx| Weekday::from_usize(idx).unwrap().to_string(), dataset_average, options.integer("width"));
+
+        for call in dms.calls_at_least(min_duration.into()) {
+            let index = dms.local_date(call).weekday() as usize;
+            graph.add(0, index, TimeQuantity::from(call.duration()));
+        }
+
+        report.push(graph.finish("Call Duration by Day of Week"));
+
+        Ok(report)
+    }
+}
+
+pub struct CallGraph;
+
+impl Analysis for CallGraph {
+    fn id(&self) -> &'static str {
+        "call-graph"
+    }
+
+    fn description(&self) -> &'static str {
+        "Call Duration Graph (Daily Graph)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let min_duration = options.duration("min-call-duration");
+        let mut report = Report::new(self.id(), format!("Call Graph (10m groupings, min = {min_duration})"));
+
+        let mut graph = Graph::new(dms.authors.labels(), 5 * 6 + 3, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 6, minutes = (idx % 6) * 10), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+
+        for call in dms.calls_at_least(min_duration.into()) {
+            spread_call(dms.local_time(call), call.duration(), 10 * 60 * 1000, |idx, ms| {
+                graph.add(call.author.index(), idx, TimeQuantity::from(ms));
+            });
+        }
+        graph.add(0, 24 * 6 - 1, TimeQuantity::ZERO);
+
+        report.push(graph.finish("Call Graph"));
+
+        Ok(report)
+    }
+}
+
+pub struct CallPng;
+
+impl Analysis for CallPng {
+    fn id(&self) -> &'static str {
+        "call-png"
+    }
+
+    fn description(&self) -> &'static str {
+        "Call Duration Graph PNG Export (Daily Graph)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        const NUM_QUANTITIES: usize = 24 * 60 * 4;
+        const QUANTITY_PER: usize = 1000 * 60 * 60 * 24 / NUM_QUANTITIES;
+
+        let mut report = Report::new(self.id(), "Call Graph Image (15s groupings)");
+
+        let mut graph = Graph::new(dms.authors.labels(), 11 * NUM_QUANTITIES / 48, |idx| TimeQuantity::from(idx * QUANTITY_PER).short(), dataset_sum, 0).with_colors(dms.authors.colors());
+        for call in dms.calls_at_least(options.duration("min-call-duration").into()) {
+            spread_call(dms.local_time(call), call.duration(), QUANTITY_PER, |idx, ms| {
+                graph.add(call.author.index(), idx, TimeQuantity::from(ms));
+            });
+        }
+        graph.add(0, NUM_QUANTITIES - 1, TimeQuantity::ZERO);
+
+        let style = options.chart_style(NUM_QUANTITIES as u32, (NUM_QUANTITIES as f64 / std::f64::consts::TAU).ceil() as u32);
+        let path = PathBuf::from(format!("Call Graph - {channel_name} - {id}.png", channel_name = dms.channel.name, id = dms.channel.id));
+        png::write(&graph.finish("Call Graph"), &style, &path)?;
+
+        report.push(Section::File(path));
+
+        Ok(report)
+    }
+}
+
+fn spread_call(start: NaiveDateTime, duration: TimeDelta, bucket_ms: usize, mut add: impl FnMut(usize, usize)) {
+    const DAY_MS: usize = 1000 * 60 * 60 * 24;
+
+    let start = start.time();
+    let offset = start.num_seconds_from_midnight() as usize * 1000 + start.nanosecond() as usize / 1_000_000;
+    let mut index = offset / bucket_ms;
+    let mut remaining = duration.num_milliseconds() as usize;
+    let mut chunk = (bucket_ms - offset % bucket_ms).min(remaining);
+    while remaining > 0 {
+        add(index % (DAY_MS / bucket_ms), chunk);
+        remaining -= chunk;
+        index += 1;
+        chunk = remaining.min(bucket_ms);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::analysis::{month_idx, month_label, share, AnalysisOptions, OptionSpec, GRAPH_WIDTH, MIN_CALL_DURATION};
+use crate::data::{dataset_sum, BasisPoints, Graph, TimeQuantity};
+use crate::report::{Cell, Report, Table};
+use crate::serde_structs::{Call, DirectMessages};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, TimeDelta};
+
+pub trait Comparison: Sync {
+    fn id(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn options(&self) -> &'static [OptionSpec] {
+        &[]
+    }
+
+    fn run(&self, channels: &[DirectMessages], options: &AnalysisOptions) -> Result<Report>;
+}
+
+pub const COMPARISONS: &[&dyn Comparison] = &[
+    &MessagesPerMonth,
+    &MessageShareOverTime,
+    &TotalCallTime,
+    &MostTalkedTo,
+];
+
+pub fn find(id: &str) -> Option<&'static dyn Comparison> {
+    COMPARISONS.iter().copied().find(|comparison| comparison.id() == id)
+}
+
+fn channel_names(channels: &[DirectMessages]) -> Vec<&str> {
+    channels.iter().map(|dms| dms.channel.name.as_str()).collect()
+}
+
+fn first_month(channels: &[DirectMessages]) -> Option<NaiveDate> {
+    channels.iter().flat_map(|dms| dms.texts().map(|text| dms.local_date(text)).chain(dms.calls().map(|call| dms.local_date(call)))).min().map(|date| date.with_day(1).expect("Valid date"))
+}
+
+pub struct MessagesPerMonth;
+
+impl Comparison for MessagesPerMonth {
+    fn id(&self) -> &'static str {
+        "messages-per-month"
+    }
+
+    fn description(&self) -> &'static str {
+        "Messages per Channel (Monthly Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, channels: &[DirectMessages], options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Messages per Channel per Month");
+        let Some(start) = first_month(channels) else { return Ok(report) };
+
+        let mut graph = Graph::new(channel_names(channels), 0, month_label(start), dataset_sum, options.integer("width"));
+        for (channel_idx, dms) in channels.iter().enumerate() {
+            for text in dms.texts() {
+                graph.add(channel_idx, month_idx(start, dms.local_date(text)), 1);
+            }
+        }
+
+        report.push(graph.finish("Messages per Channel per Month"));
+
+        Ok(report)
+    }
+}
+
+pub struct MessageShareOverTime;
+
+impl Comparison for MessageShareOverTime {
+    fn id(&self) -> &'static str {
+        "message-share-over-time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Relative Share of Messages per Channel (Monthly Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, channels: &[DirectMessages], options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Relative Share of Messages per Month");
+        let Some(start) = first_month(channels) else { return Ok(report) };
+
+        let counts = channels.iter().map(|dms| dms.texts().fold(Vec::<usize>::new(), |mut counts, text| {
+            let idx = month_idx(start, dms.local_date(text));
+            if counts.len() <= idx {
+                counts.resize(idx + 1, 0);
+            }
+            counts[idx] += 1;
+            counts
+        })).collect::<Vec<_>>();
+        let months = counts.iter().map(Vec::len).max().unwrap_or(0);
+        let totals = (0..months).map(|idx| counts.iter().map(|counts| counts.get(idx).copied().unwrap_or(0)).sum::<usize>()).collect::<Vec<_>>();
+
+        let mut graph = Graph::new(channel_names(channels), 0, month_label(start), dataset_sum, options.integer("width"));
+        for (channel_idx, counts) in counts.iter().enumerate() {
+            for (idx, count) in counts.iter().copied().enumerate().filter(|(_, count)| *count > 0) {
+                graph.add(channel_idx, idx, BasisPoints::of(count, totals[idx]));
+            }
+        }
+
+        report.push(graph.finish("Relative Share of Messages per Month"));
+
+        Ok(report)
+    }
+}
+
+pub struct TotalCallTime;
+
+impl Comparison for TotalCallTime {
+    fn id(&self) -> &'static str {
+        "total-call-time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Total Call Time per Channel"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[MIN_CALL_DURATION, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, channels: &[DirectMessages], options: &AnalysisOptions) -> Result<Report> {
+        let min_duration = options.duration("min-call-duration");
+        let mut report = Report::new(self.id(), format!("Total Call Time per Channel (min = {min_duration})"));
+
+        let mut totals = channels.iter().map(|dms| (dms, dms.calls_at_least(min_duration.into()).count(), dms.calls_at_least(min_duration.into()).map(Call::duration).sum::<TimeDelta>())).collect::<Vec<_>>();
+        totals.sort_by_key(|(_, _, duration)| std::cmp::Reverse(*duration));
+
+        let mut table = Table::ranked("Total Call Time", vec!["channel", "channel_id", "calls", "total length", "average length"]);
+        for (dms, calls, duration) in totals {
+            let average = if calls == 0 { Cell::Empty } else { Cell::Duration(TimeQuantity::from(duration / calls as i32)) };
+            table.push(vec![Cell::Text(dms.channel.name.clone()), Cell::Id(dms.channel.id), Cell::Integer(calls), Cell::Duration(TimeQuantity::from(duration)), average]);
+        }
+        report.push(table);
+
+        if let Some(start) = first_month(channels) {
+            let mut graph = Graph::new(channel_names(channels), 0, month_label(start), dataset_sum, options.integer("width"));
+            for (channel_idx, dms) in channels.iter().enumerate() {
+                for call in dms.calls_at_least(min_duration.into()) {
+                    graph.add(channel_idx, month_idx(start, dms.local_date(call)), TimeQuantity::from(call.duration()));
+                }
+            }
+            report.push(graph.finish("Call Time per Channel per Month"));
+        }
+
+        Ok(report)
+    }
+}
+
+pub struct MostTalkedTo;
+
+impl Comparison for MostTalkedTo {
+    fn id(&self) -> &'static str {
+        "most-talked-to"
+    }
+
+    fn description(&self) -> &'static str {
+        "Who You Talk to Most"
+    }
+
+    fn run(&self, channels: &[DirectMessages], _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Who You Talk to Most");
+        let total_messages = channels.iter().map(|dms| dms.texts().count()).sum::<usize>();
+
+        let mut rows = channels.iter().map(|dms| {
+            let messages = dms.texts().count();
+            let words = dms.texts().map(|text| text.content.split_whitespace().count()).sum::<usize>();
+            let call_time = dms.calls().map(Call::duration).sum::<TimeDelta>();
+            let first = dms.texts().map(|text| dms.local_time(text)).min();
+            let last = dms.texts().map(|text| dms.local_time(text)).max();
+            (messages, vec![
+                Cell::Text(dms.channel.name.clone()),
+                Cell::Id(dms.channel.id),
+                Cell::Integer(messages),
+                share(messages, total_messages),
+                Cell::Integer(words),
+                Cell::Duration(TimeQuantity::from(call_time)),
+                first.map_or(Cell::Empty, Cell::Timestamp),
+                last.map_or(Cell::Empty, Cell::Timestamp),
+            ])
+        }).collect::<Vec<_>>();
+        rows.sort_by_key(|(messages, _)| std::cmp::Reverse(*messages));
+
+        let mut table = Table::ranked("Most Talked To", vec!["channel", "channel_id", "messages", "share", "words", "call time", "first message", "last message"]);
+        for (_, row) in rows {
+            table.push(row);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,211 @@
+use crate::analysis::{limit, month_idx, month_label, share, Analysis, AnalysisOptions, OptionSpec, GRAPH_WIDTH, SESSION_GAP};
+use crate::data::{dataset_median, dataset_sum, Graph, TimeQuantity};
+use crate::percentile;
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{AuthorIdx, DirectMessages, TextMessage};
+use anyhow::Result;
+use chrono::{Datelike, TimeDelta, Timelike};
+use itertools::Itertools;
+
+pub struct LongestTimeBetweenMessages;
+
+impl Analysis for LongestTimeBetweenMessages {
+    fn id(&self) -> &'static str {
+        "longest-time-between-messages"
+    }
+
+    fn description(&self) -> &'static str {
+        "Longest Time Between Messages"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(25)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Longest Time Between Messages");
+
+        let mut differences = dms.texts().tuple_windows().map(|(a, b)| (b.timestamp - a.timestamp, a, b)).collect::<Vec<_>>();
+        differences.sort_by_key(|(diff, _, _)| *diff);
+
+        let mut table = Table::ranked("Longest Time Between Messages", vec!["diff", "first_timestamp", "second_timestamp", "first_id", "second_id", "content", "author"]);
+        for (diff, a, b) in differences.into_iter().rev().take(options.integer("limit")) {
+            table.push(vec![Cell::Duration(TimeQuantity::from(diff)), Cell::Timestamp(dms.local_time(a)), Cell::Timestamp(dms.local_time(b)), Cell::Id(a.id), Cell::Id(b.id), Cell::Content(a.content.to_string()), Cell::Text(dms.authors[a.author].label.clone())]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct LongestTimeBetweenDifferentUsers;
+
+impl Analysis for LongestTimeBetweenDifferentUsers {
+    fn id(&self) -> &'static str {
+        "longest-time-between-different-users"
+    }
+
+    fn description(&self) -> &'static str {
+        "Longest Time Between Messages from Different Users"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(25)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Longest Time (and most messages) Between Different Users");
+        let mut differences = Vec::new();
+
+        let Some(mut prev_text) = dms.texts().next() else { return Ok(report) };
+        let mut messages_between = 1_usize;
+
+        for text in dms.texts().skip(1) {
+            if text.author != prev_text.author {
+                differences.push((text.timestamp - prev_text.timestamp, prev_text, text, messages_between));
+                prev_text = text;
+                messages_between = 1;
+            } else {
+                messages_between += 1;
+            }
+        }
+
+        let columns = vec!["diff", "messages_between", "first_timestamp", "second_timestamp", "first_id", "second_id", "first_content", "second_content"];
+        let row = |&(diff, first, second, messages_between): &(TimeDelta, &TextMessage, &TextMessage, usize)| vec![Cell::Duration(TimeQuantity::from(diff)), Cell::Integer(messages_between), Cell::Timestamp(dms.local_time(first)), Cell::Timestamp(dms.local_time(second)), Cell::Id(first.id), Cell::Id(second.id), Cell::Content(first.content.to_string()), Cell::Content(second.content.to_string())];
+
+        differences.sort_by_key(|(diff, _, _, _)| *diff);
+        let mut by_time = Table::ranked("Longest Time Between Different Users", columns.clone());
+        by_time.rows.extend(differences.iter().rev().take(options.integer("limit")).map(row));
+        report.push(by_time);
+
+        report.push(Section::Text(String::new()));
+
+        differences.sort_by_key(|(_, _, _, messages_between)| *messages_between);
+        let mut by_messages = Table::ranked("Most Messages Between Different Users", columns);
+        by_messages.rows.extend(differences.iter().rev().take(options.integer("limit")).map(row));
+        report.push(by_messages);
+
+        Ok(report)
+    }
+}
+
+pub struct ResponseTimes;
+
+impl Analysis for ResponseTimes {
+    fn id(&self) -> &'static str {
+        "response-times"
+    }
+
+    fn description(&self) -> &'static str {
+        "Response Times per Author"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[SESSION_GAP, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let gap = options.duration("session-gap");
+        let mut report = Report::new(self.id(), format!("Response Times per Author (ignoring silences over {gap})"));
+
+        // the time from the other party's last message until the reply, longer silences start a new conversation instead
+        let responses = dms.texts().tuple_windows().filter(|(message, reply)| message.author != reply.author).map(|(message, reply)| (message, reply, reply.timestamp - message.timestamp)).filter(|(_, _, latency)| *latency <= TimeDelta::from(gap)).collect::<Vec<_>>();
+        let Some(start) = responses.iter().map(|(_, reply, _)| dms.local_date(*reply)).min().map(|date| date.with_day(1).expect("Valid date")) else { return Ok(report) };
+
+        let columns = vec!["author", "responses", "median", "p90", "p99", "mean"];
+        let row = |author: AuthorIdx, latencies: Vec<TimeDelta>| {
+            let latencies = latencies.into_iter().sorted().collect::<Vec<_>>();
+            let stat = |pct| percentile(&latencies, pct).map_or(Cell::Empty, |latency| Cell::Duration(TimeQuantity::from(latency)));
+            vec![Cell::Text(dms.authors[author].label.clone()), Cell::Integer(latencies.len()), stat(50), stat(90), stat(99), Cell::Duration(TimeQuantity::from(latencies.iter().sum::<TimeDelta>() / latencies.len() as i32))]
+        };
+
+        let mut table = Table::new("Response Times", columns.clone());
+        for (author, latencies) in responses.iter().map(|(_, reply, latency)| (reply.author, *latency)).into_group_map().into_iter().sorted_by_key(|(author, _)| *author) {
+            table.push(row(author, latencies));
+        }
+        report.push(table);
+
+        report.push(Section::Heading("By Year".to_owned()));
+        let mut table = Table::new("Response Times by Year", [&["year"][..], &columns].concat());
+        for ((year, author), latencies) in responses.iter().map(|(_, reply, latency)| ((dms.local_date(*reply).year(), reply.author), *latency)).into_group_map().into_iter().sorted_by_key(|(key, _)| *key) {
+            table.push([vec![Cell::Text(year.to_string())], row(author, latencies)].concat());
+        }
+        report.push(table);
+
+        let mut by_hour = Graph::new(dms.authors.labels(), 0, |idx| format!("{idx:02}h"), dataset_median, options.integer("width")).with_colors(dms.authors.colors()).with_buckets(24);
+        let mut by_month = Graph::new(dms.authors.labels(), 0, month_label(start), dataset_median, options.integer("width")).with_colors(dms.authors.colors());
+        for (message, reply, latency) in &responses {
+            by_hour.add(reply.author.index(), dms.local_time(*message).hour() as usize, TimeQuantity::from(*latency));
+            by_month.add(reply.author.index(), month_idx(start, dms.local_date(*reply)), TimeQuantity::from(*latency));
+        }
+        report.push(by_hour.finish("Median Response Time by Hour of Day (of the message replied to)"));
+        report.push(by_month.finish("Median Response Time per Month"));
+
+        Ok(report)
+    }
+}
+
+pub struct Bursts;
+
+impl Analysis for Bursts {
+    fn id(&self) -> &'static str {
+        "bursts"
+    }
+
+    fn description(&self) -> &'static str {
+        "Double Texting and Message Bursts"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[SESSION_GAP, limit(25), GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        const MAX_GRAPHED_LENGTH: usize = 20;
+
+        let gap = options.duration("session-gap");
+        let mut report = Report::new(self.id(), format!("Double Texting and Message Bursts (split at silences over {gap})"));
+
+        // a turn is every message someone sends before the other party says anything, longer silences start a new turn
+        let mut turns = Vec::<(usize, &TextMessage, &TextMessage)>::new();
+        for text in dms.texts() {
+            match turns.last_mut() {
+                Some((len, _, last)) if text.author == last.author && text.timestamp - last.timestamp <= TimeDelta::from(gap) => {
+                    *len += 1;
+                    *last = text;
+                },
+                _ => turns.push((1, text, text)),
+            }
+        }
+
+        let mut table = Table::new("Bursts per Author", vec!["author", "turns", "messages", "average burst", "single", "double", "triple", "4 or more", "longest"]);
+        for (author, _) in dms.authors.iter() {
+            let lengths = turns.iter().filter(|(_, first, _)| first.author == author).map(|(len, _, _)| *len).collect::<Vec<_>>();
+            let messages = lengths.iter().sum::<usize>();
+            let count = |f: fn(usize) -> bool| lengths.iter().filter(|len| f(**len)).count();
+            table.push(vec![Cell::Text(dms.authors[author].label.clone()), Cell::Integer(lengths.len()), Cell::Integer(messages), if lengths.is_empty() { Cell::Empty } else { Cell::Float(messages as f64 / lengths.len() as f64) }, share(count(|len| len == 1), lengths.len()), share(count(|len| len == 2), lengths.len()), share(count(|len| len == 3), lengths.len()), share(count(|len| len >= 4), lengths.len()), lengths.iter().max().map_or(Cell::Empty, |len| Cell::Integer(*len))]);
+        }
+        report.push(table);
+
+        let mut graph = Graph::new(dms.authors.labels(), 0, |idx| if idx + 1 == MAX_GRAPHED_LENGTH { format!("{MAX_GRAPHED_LENGTH}+ messages") } else { format!("{n} message{s}", n = idx + 1, s = if idx == 0 { "" } else { "s" }) }, dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+        for (len, first, _) in &turns {
+            graph.add(first.author.index(), (*len).min(MAX_GRAPHED_LENGTH) - 1, 1);
+        }
+
+        turns.sort_by_key(|(len, _, _)| std::cmp::Reverse(*len));
+        report.push(Section::Heading("Biggest Monologues".to_owned()));
+        let mut table = Table::ranked("Biggest Monologues", vec!["messages", "author", "duration", "start_timestamp", "end_timestamp", "first_id", "last_id"]);
+        for (len, first, last) in turns.iter().take(options.integer("limit")) {
+            table.push(vec![Cell::Integer(*len), Cell::Text(dms.authors[first.author].label.clone()), Cell::Duration(TimeQuantity::from(last.timestamp - first.timestamp)), Cell::Timestamp(dms.local_time(*first)), Cell::Timestamp(dms.local_time(*last)), Cell::Id(first.id), Cell::Id(last.id)]);
+        }
+        report.push(table);
+
+        report.push(graph.finish("Burst Length Distribution (turns per number of consecutive messages)"));
+
+        Ok(report)
+    }
+}
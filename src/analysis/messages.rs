@@ -0,0 +1,164 @@
+use crate::analysis::{limit, Analysis, AnalysisOptions, OptionSpec};
+use crate::report::{Cell, Report, Table};
+use crate::serde_structs::DirectMessages;
+use anyhow::Result;
+use chrono::NaiveDate;
+use fxhash::FxHashMap;
+
+pub struct FirstMessage;
+
+impl Analysis for FirstMessage {
+    fn id(&self) -> &'static str {
+        "first-message"
+    }
+
+    fn description(&self) -> &'static str {
+        "First Message"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "First Message");
+        let mut first_messages = vec![None; dms.authors.len()];
+
+        for text in dms.texts() {
+            if first_messages[text.author.index()].is_none() {
+                first_messages[text.author.index()] = Some(text);
+            }
+        }
+
+        let mut table = Table::new("First Message", vec!["author", "timestamp", "id", "content"]);
+        for ((_, author), first_message) in dms.authors.iter().zip(first_messages) {
+            let author = Cell::Text(author.label.clone());
+            if let Some(text) = first_message {
+                table.push(vec![author, Cell::Timestamp(dms.local_time(text)), Cell::Id(text.id), Cell::Content(text.content.to_string())]);
+            } else {
+                table.push(vec![author, Cell::Empty, Cell::Empty, Cell::Empty]);
+            }
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct MostSaidWords;
+
+impl Analysis for MostSaidWords {
+    fn id(&self) -> &'static str {
+        "most-said-words"
+    }
+
+    fn description(&self) -> &'static str {
+        "100 Most Said Words"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(100)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let limit = options.integer("limit");
+        let mut report = Report::new(self.id(), format!("{limit} Most Said Words"));
+        let mut map = FxHashMap::<String, usize>::default();
+
+        for text in dms.texts() {
+            let content = text.content_alphanumeric_lowercase();
+            for word in content.split_ascii_whitespace() {
+                *map.entry(word.to_owned()).or_insert(0) += 1;
+            }
+        }
+
+        let mut map = map.into_iter().collect::<Vec<_>>();
+        map.sort_by_key(|(_, b)| usize::MAX - *b);
+        let mut table = Table::ranked("Most Said Words", vec!["word", "count"]);
+        for (word, count) in map.into_iter().take(limit) {
+            table.push(vec![Cell::Text(word), Cell::Integer(count)]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct WordsAndCharactersWritten;
+
+impl Analysis for WordsAndCharactersWritten {
+    fn id(&self) -> &'static str {
+        "words-and-characters-written"
+    }
+
+    fn description(&self) -> &'static str {
+        "Words and Characters Written"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Words and Characters Written (per person)");
+
+        let mut quantities = vec![(0_usize, 0_usize); dms.authors.len()];
+        for text in dms.texts() {
+            let written = text.content_alphanumeric_lowercase();
+            let words = written.split_ascii_whitespace().count();
+            let (total_words, total_characters) = &mut quantities[text.author.index()];
+            *total_words += words;
+            *total_characters += text.content.len();
+        }
+
+        let mut table = Table::new("Words and Characters Written", vec!["author", "words", "characters"]);
+        for ((_, author), (words, characters)) in dms.authors.iter().zip(quantities) {
+            table.push(vec![Cell::Text(author.label.clone()), Cell::Integer(words), Cell::Integer(characters)]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
+
+pub struct MostCharactersSaidInADay;
+
+impl Analysis for MostCharactersSaidInADay {
+    fn id(&self) -> &'static str {
+        "most-characters-said-in-a-day"
+    }
+
+    fn description(&self) -> &'static str {
+        "Most Characters Said in a Day"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[limit(25)];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        #[derive(Default)]
+        struct Measurement {
+            messages: usize,
+            words: usize,
+            characters: usize,
+            attachments: usize,
+        }
+
+        let mut report = Report::new(self.id(), "Most Messages, Words, Characters, and Attachments Said In Day (sorted by messages)");
+
+        let mut map = FxHashMap::<NaiveDate, Measurement>::default();
+        for text in dms.texts() {
+            let date = dms.local_date(text);
+            let entry = map.entry(date).or_default();
+            entry.messages += 1;
+            entry.words += text.content_alphanumeric_lowercase().split_ascii_whitespace().count();
+            entry.characters += text.content.len();
+            entry.attachments += text.attachments.len();
+        }
+
+        let mut map = map.into_iter().collect::<Vec<_>>();
+        map.sort_by_key(|(_, b)| usize::MAX - b.messages);
+        let mut table = Table::ranked("Most Said In a Day", vec!["date", "messages", "words", "characters", "attachments"]);
+        for (date, measurement) in map.into_iter().take(options.integer("limit")) {
+            table.push(vec![Cell::Date(date), Cell::Integer(measurement.messages), Cell::Integer(measurement.words), Cell::Integer(measurement.characters), Cell::Integer(measurement.attachments)]);
+        }
+        report.push(table);
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,178 @@
+pub mod activity;
+pub mod authors;
+pub mod calls;
+pub mod comparison;
+pub mod gaps;
+pub mod messages;
+pub mod rates;
+pub mod sessions;
+
+use crate::data::{BasisPoints, TimeQuantity};
+use crate::render::ChartStyle;
+use crate::report::{Cell, Report};
+use crate::serde_structs::DirectMessages;
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate};
+use std::fmt::{Display, Formatter};
+
+pub trait Analysis: Sync {
+    fn id(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn options(&self) -> &'static [OptionSpec] {
+        &[]
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report>;
+}
+
+pub const ANALYSES: &[&dyn Analysis] = &[
+    &messages::FirstMessage,
+    &activity::TextingFrequency,
+    &calls::TopCallLengths,
+    &calls::TotalCallLengths,
+    &gaps::LongestTimeBetweenMessages,
+    &gaps::LongestTimeBetweenDifferentUsers,
+    &messages::MostSaidWords,
+    &messages::WordsAndCharactersWritten,
+    &messages::MostCharactersSaidInADay,
+    &calls::CallStartTimeOfDay,
+    &activity::TextTimeOfDay,
+    &calls::CallDurationByMonth,
+    &calls::CallDurationByDayOfWeek,
+    &calls::CallGraph,
+    &calls::CallPng,
+    &rates::CapitalizationRates,
+    &rates::EditRates,
+    &authors::NameHistory,
+    &sessions::Sessions,
+    &gaps::ResponseTimes,
+    &sessions::ConversationStarters,
+    &gaps::Bursts,
+    &activity::Streaks,
+    &activity::CalendarHeatmap,
+];
+
+pub fn find(id: &str) -> Option<&'static dyn Analysis> {
+    ANALYSES.iter().copied().find(|analysis| analysis.id() == id)
+}
+
+pub fn month_idx(start: NaiveDate, date: NaiveDate) -> usize {
+    ((date.year() - start.year()) * 12 + date.month0() as i32 - start.month0() as i32) as usize
+}
+
+pub fn month_label(start: NaiveDate) -> impl Fn(usize) -> String {
+    move |idx| {
+        let months = start.month0() as usize + idx;
+        NaiveDate::from_ymd_opt(start.year() + (months / 12) as i32, (months % 12) as u32 + 1, 1).expect("Valid date").format("%b %Y").to_string()
+    }
+}
+
+pub fn share(part: usize, whole: usize) -> Cell {
+    Cell::Percentage(BasisPoints::of(part, whole).0 as f64 / 100.0)
+}
+
+pub const fn limit(default: usize) -> OptionSpec {
+    OptionSpec { key: "limit", description: "How many entries to list", default: OptionValue::Integer(default) }
+}
+
+pub const GRAPH_WIDTH: OptionSpec = OptionSpec { key: "width", description: "Width of the bars in the text graph", default: OptionValue::Integer(50) };
+
+pub const MIN_CALL_DURATION: OptionSpec = OptionSpec { key: "min-call-duration", description: "Calls shorter than this are ignored", default: OptionValue::Duration(TimeQuantity::new(0, 0, 0, 15, 0)) };
+
+pub const SESSION_GAP: OptionSpec = OptionSpec { key: "session-gap", description: "Silence after which a new conversation starts", default: OptionValue::Duration(TimeQuantity::new(0, 1, 0, 0, 0)) };
+
+pub struct OptionSpec {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub default: OptionValue,
+}
+
+#[derive(Copy, Clone)]
+pub enum OptionValue {
+    Integer(usize),
+    Duration(TimeQuantity),
+    Flag(bool),
+}
+
+impl OptionValue {
+    fn parse_as(&self, value: &str) -> Result<Self> {
+        Ok(match self {
+            OptionValue::Integer(_) => OptionValue::Integer(value.parse().with_context(|| format!("Expected a number, found '{value}'"))?),
+            OptionValue::Duration(_) => OptionValue::Duration(value.parse()?),
+            OptionValue::Flag(_) => OptionValue::Flag(value.parse().with_context(|| format!("Expected true or false, found '{value}'"))?),
+        })
+    }
+}
+
+impl Display for OptionValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionValue::Integer(n) => write!(f, "{n}"),
+            OptionValue::Duration(duration) => write!(f, "{duration}"),
+            OptionValue::Flag(flag) => write!(f, "{flag}"),
+        }
+    }
+}
+
+pub struct AnalysisOptions {
+    values: Vec<(&'static str, OptionValue)>,
+    style: ChartStyle,
+    custom_size: bool,
+}
+
+impl AnalysisOptions {
+    pub fn new<'a>(id: &str, specs: &[OptionSpec], overrides: impl IntoIterator<Item=(&'a str, &'a str)>) -> Result<Self> {
+        let mut values = specs.iter().map(|spec| (spec.key, spec.default)).collect::<Vec<_>>();
+        for (key, value) in overrides {
+            let Some((_, current)) = values.iter_mut().find(|(spec_key, _)| *spec_key == key) else {
+                bail!("Analysis '{id}' has no option '{key}'")
+            };
+            *current = current.parse_as(value).with_context(|| format!("Invalid value for '{id}.{key}'"))?;
+        }
+        Ok(Self { values, style: ChartStyle::default(), custom_size: false })
+    }
+
+    pub fn with_style(self, style: ChartStyle, custom_size: bool) -> Self {
+        Self { style, custom_size, ..self }
+    }
+
+    pub fn style(&self) -> &ChartStyle {
+        &self.style
+    }
+
+    // the style for images an analysis writes itself, in the given size unless the user asked for one
+    pub fn chart_style(&self, width: u32, height: u32) -> ChartStyle {
+        if self.custom_size {
+            self.style.clone()
+        } else {
+            ChartStyle { width, height, ..self.style.clone() }
+        }
+    }
+
+    fn get(&self, key: &str) -> OptionValue {
+        self.values.iter().find(|(spec_key, _)| *spec_key == key).map(|(_, value)| *value).unwrap_or_else(|| panic!("Undeclared analysis option '{key}'"))
+    }
+
+    pub fn integer(&self, key: &str) -> usize {
+        match self.get(key) {
+            OptionValue::Integer(n) => n,
+            _ => panic!("Analysis option '{key}' is not an integer"),
+        }
+    }
+
+    pub fn duration(&self, key: &str) -> TimeQuantity {
+        match self.get(key) {
+            OptionValue::Duration(duration) => duration,
+            _ => panic!("Analysis option '{key}' is not a duration"),
+        }
+    }
+
+    pub fn flag(&self, key: &str) -> bool {
+        match self.get(key) {
+            OptionValue::Flag(flag) => flag,
+            _ => panic!("Analysis option '{key}' is not a flag"),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::analysis::{share, Analysis, AnalysisOptions};
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{DirectMessages, TextMessage};
+use anyhow::Result;
+use chrono::Datelike;
+
+fn annual_rates(dms: &DirectMessages, report: &mut Report, columns: [&'static str; 2], include: impl Fn(&TextMessage) -> bool, hit: impl Fn(&TextMessage) -> bool) -> Result<()> {
+    let Some(first_year) = dms.texts().map(|text| dms.local_date(text).year()).min() else { return Ok(()) };
+    let Some(last_year) = dms.texts().map(|text| dms.local_date(text).year()).max() else { return Ok(()) };
+
+    for year in first_year..=last_year {
+        let mut quantities = vec![(0_usize, 0_usize); dms.authors.len()];
+
+        for text in dms.texts().filter(|text| dms.local_date(*text).year() == year && include(text)) {
+            let (hits, misses) = &mut quantities[text.author.index()];
+            if hit(text) {
+                *hits += 1;
+            } else {
+                *misses += 1;
+            }
+        }
+
+        report.push(Section::Heading(year.to_string()));
+
+        let mut table = Table::new(year.to_string(), vec!["author", columns[0], "total", columns[1]]);
+        for ((_, author), (hits, misses)) in dms.authors.iter().zip(quantities) {
+            let total = hits + misses;
+            table.push(vec![Cell::Text(author.label.clone()), Cell::Integer(hits), Cell::Integer(total), share(hits, total)]);
+        }
+        report.push(table);
+    }
+
+    Ok(())
+}
+
+pub struct CapitalizationRates;
+
+impl Analysis for CapitalizationRates {
+    fn id(&self) -> &'static str {
+        "capitalization-rates"
+    }
+
+    fn description(&self) -> &'static str {
+        "Capitalization Rates (Annual Buckets)"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Capitalization Rates");
+        annual_rates(dms, &mut report, ["capitalized", "rate"], |text| text.content.chars().next().is_some_and(char::is_alphabetic), |text| text.content.chars().next().is_some_and(char::is_uppercase))?;
+        Ok(report)
+    }
+}
+
+pub struct EditRates;
+
+impl Analysis for EditRates {
+    fn id(&self) -> &'static str {
+        "edit-rates"
+    }
+
+    fn description(&self) -> &'static str {
+        "Edited Rates (Annual Buckets)"
+    }
+
+    fn run(&self, dms: &DirectMessages, _: &AnalysisOptions) -> Result<Report> {
+        let mut report = Report::new(self.id(), "Edit Rates");
+        annual_rates(dms, &mut report, ["edited", "rate"], |_| true, |text| text.edited_timestamp.is_some())?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,242 @@
+use crate::analysis::{limit, month_idx, month_label, share, Analysis, AnalysisOptions, OptionSpec, OptionValue, GRAPH_WIDTH, SESSION_GAP};
+use crate::data::{dataset_sum, BasisPoints, Graph, TimeQuantity};
+use crate::percentile;
+use crate::report::{Cell, Report, Section, Table};
+use crate::serde_structs::{AuthorIdx, DirectMessages, Timestamped};
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, TimeDelta};
+use itertools::Itertools;
+
+const LENGTH_BUCKETS: &[(i64, &str)] = &[(1, "under 1m"), (15, "1m to 15m"), (60, "15m to 1h"), (3 * 60, "1h to 3h"), (8 * 60, "3h to 8h"), (i64::MAX, "8h or more")];
+const MESSAGE_BUCKETS: &[(usize, &str)] = &[(0, "0 (calls only)"), (1, "1"), (5, "2 to 5"), (20, "6 to 20"), (50, "21 to 50"), (100, "51 to 100"), (usize::MAX, "over 100")];
+
+// a conversation, a run of messages (and calls) where no silence is longer than the session gap
+pub struct Session {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub first_id: u64,
+    pub last_id: u64,
+    pub starter: AuthorIdx,
+    pub closer: AuthorIdx,
+    pub messages: usize,
+    pub calls: usize,
+}
+
+impl Session {
+    pub fn length(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+impl Timestamped for Session {
+    fn author(&self) -> AuthorIdx {
+        self.starter
+    }
+
+    fn timestamp(&self) -> NaiveDateTime {
+        self.start
+    }
+}
+
+pub fn sessionize(dms: &DirectMessages, gap: TimeDelta, include_calls: bool) -> Vec<Session> {
+    let texts = dms.texts().map(|text| (text.timestamp, text.timestamp, text.author, text.id, false));
+    let calls = dms.calls().filter(|_| include_calls).map(|call| (call.start_timestamp, call.end_timestamp.max(call.start_timestamp), call.author, call.id, true));
+
+    let mut sessions = Vec::<Session>::new();
+    for (start, end, author, id, is_call) in texts.chain(calls).sorted_by_key(|(start, ..)| *start) {
+        match sessions.last_mut().filter(|session| start - session.end <= gap) {
+            Some(session) => {
+                session.end = session.end.max(end);
+                session.last_id = id;
+                session.closer = author;
+            },
+            None => sessions.push(Session { start, end, first_id: id, last_id: id, starter: author, closer: author, messages: 0, calls: 0 }),
+        }
+        let session = sessions.last_mut().expect("Session was just pushed or extended");
+        if is_call {
+            session.calls += 1;
+        } else {
+            session.messages += 1;
+        }
+    }
+    sessions
+}
+
+pub struct Sessions;
+
+impl Analysis for Sessions {
+    fn id(&self) -> &'static str {
+        "sessions"
+    }
+
+    fn description(&self) -> &'static str {
+        "Conversation Sessions (Lifetime Graph; Weekly Buckets)"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[SESSION_GAP, OptionSpec { key: "include-calls", description: "Whether calls count towards sessions", default: OptionValue::Flag(false) }, limit(25), GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let gap = options.duration("session-gap");
+        let include_calls = options.flag("include-calls");
+        let mut report = Report::new(self.id(), format!("Conversation Sessions (gap = {gap}{calls})", calls = if include_calls { ", including calls" } else { "" }));
+
+        let mut sessions = sessionize(dms, gap.into(), include_calls);
+        let Some(earliest_session_day) = sessions.iter().map(|session| dms.local_date(session)).min() else { return Ok(report) };
+        let lengths = sessions.iter().map(Session::length).sorted().collect::<Vec<_>>();
+        let messages = sessions.iter().map(|session| session.messages).sorted().collect::<Vec<_>>();
+        let total_length = lengths.iter().sum::<TimeDelta>();
+
+        report.push(Section::Stats(vec![
+            ("sessions".to_owned(), Cell::Integer(sessions.len())),
+            ("total length".to_owned(), Cell::Duration(TimeQuantity::from(total_length))),
+            ("average length".to_owned(), Cell::Duration(TimeQuantity::from(total_length / sessions.len() as i32))),
+            ("median length".to_owned(), percentile(&lengths, 50).map_or(Cell::Empty, |length| Cell::Duration(TimeQuantity::from(length)))),
+            ("average messages per session".to_owned(), Cell::Float(messages.iter().sum::<usize>() as f64 / sessions.len() as f64)),
+            ("median messages per session".to_owned(), percentile(&messages, 50).map_or(Cell::Empty, Cell::Integer)),
+            ("single message sessions".to_owned(), Cell::Integer(messages.iter().filter(|messages| **messages == 1).count())),
+        ]));
+
+        report.push(Section::Heading("Session Length Distribution".to_owned()));
+        let mut table = Table::new("Session Length Distribution", vec!["length", "sessions", "share"]);
+        for (idx, (_, label)) in LENGTH_BUCKETS.iter().enumerate() {
+            let count = lengths.iter().filter(|length| LENGTH_BUCKETS.iter().position(|(upper, _)| length.num_minutes() < *upper) == Some(idx)).count();
+            table.push(vec![Cell::Text(label.to_string()), Cell::Integer(count), share(count, sessions.len())]);
+        }
+        report.push(table);
+
+        report.push(Section::Heading("Messages per Session".to_owned()));
+        let mut table = Table::new("Messages per Session", vec!["messages", "sessions", "share"]);
+        // sessions without messages only exist when calls count towards them
+        for (idx, (_, label)) in MESSAGE_BUCKETS.iter().enumerate().skip(if include_calls { 0 } else { 1 }) {
+            let count = messages.iter().filter(|messages| MESSAGE_BUCKETS.iter().position(|(upper, _)| *messages <= upper) == Some(idx)).count();
+            table.push(vec![Cell::Text(label.to_string()), Cell::Integer(count), share(count, sessions.len())]);
+        }
+        report.push(table);
+
+        let earliest_session_date = NaiveDate::from_yo_opt(earliest_session_day.year(), earliest_session_day.ordinal0() / 7 * 7 + 1).unwrap();
+        let mut graph = Graph::new(dms.authors.labels(), 0, |idx| earliest_session_date.checked_add_days(Days::new(idx as u64 * 7)).unwrap().format("Week of %b %d, %Y").to_string(), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+        for session in &sessions {
+            let idx = (dms.local_date(session) - earliest_session_date).num_days() as usize / 7;
+            graph.add(session.starter.index(), idx, 1);
+        }
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.length()));
+        report.push(Section::Heading("Longest Sessions".to_owned()));
+        let mut table = Table::ranked("Longest Sessions", vec!["length", "messages", "calls", "start_timestamp", "end_timestamp", "first_id", "last_id", "started_by"]);
+        for session in sessions.iter().take(options.integer("limit")) {
+            table.push(vec![Cell::Duration(TimeQuantity::from(session.length())), Cell::Integer(session.messages), Cell::Integer(session.calls), Cell::Timestamp(dms.local(session.starter, session.start)), Cell::Timestamp(dms.local(session.closer, session.end)), Cell::Id(session.first_id), Cell::Id(session.last_id), Cell::Text(dms.authors[session.starter].label.clone())]);
+        }
+        report.push(table);
+
+        report.push(graph.finish("Sessions per Week (by who started them)"));
+
+        Ok(report)
+    }
+}
+
+pub struct ConversationStarters;
+
+impl Analysis for ConversationStarters {
+    fn id(&self) -> &'static str {
+        "conversation-starters"
+    }
+
+    fn description(&self) -> &'static str {
+        "Who Starts and Ends Conversations"
+    }
+
+    fn options(&self) -> &'static [OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[SESSION_GAP, GRAPH_WIDTH];
+        OPTIONS
+    }
+
+    fn run(&self, dms: &DirectMessages, options: &AnalysisOptions) -> Result<Report> {
+        let gap = options.duration("session-gap");
+        let mut report = Report::new(self.id(), format!("Who Starts and Ends Conversations (after {gap} of silence)"));
+
+        let sessions = sessionize(dms, gap.into(), false);
+        let Some(start) = sessions.iter().map(|session| dms.local_date(session)).min().map(|date| date.with_day(1).expect("Valid date")) else { return Ok(report) };
+
+        let columns = vec!["author", "started", "started share", "ended", "ended share"];
+        let row = |author: AuthorIdx, sessions: &[&Session]| {
+            let started = sessions.iter().filter(|session| session.starter == author).count();
+            let ended = sessions.iter().filter(|session| session.closer == author).count();
+            vec![Cell::Text(dms.authors[author].label.clone()), Cell::Integer(started), share(started, sessions.len()), Cell::Integer(ended), share(ended, sessions.len())]
+        };
+
+        let all = sessions.iter().collect::<Vec<_>>();
+        let mut table = Table::new("Conversation Starters and Enders", columns.clone());
+        for (author, _) in dms.authors.iter() {
+            table.push(row(author, &all));
+        }
+        report.push(Section::Stats(vec![("conversations".to_owned(), Cell::Integer(sessions.len()))]));
+        report.push(table);
+
+        report.push(Section::Heading("By Year".to_owned()));
+        let mut table = Table::new("Conversation Starters and Enders by Year", [&["year", "conversations"][..], &columns].concat());
+        for (year, sessions) in sessions.iter().map(|session| (dms.local_date(session).year(), session)).into_group_map().into_iter().sorted_by_key(|(year, _)| *year) {
+            for (author, _) in dms.authors.iter() {
+                table.push([vec![Cell::Text(year.to_string()), Cell::Integer(sessions.len())], row(author, &sessions)].concat());
+            }
+        }
+        report.push(table);
+
+        let mut started = Vec::<Vec<usize>>::new();
+        for session in &sessions {
+            let idx = month_idx(start, dms.local_date(session));
+            if started.len() <= idx {
+                started.resize_with(idx + 1, || vec![0; dms.authors.len()]);
+            }
+            started[idx][session.starter.index()] += 1;
+        }
+        let mut graph = Graph::new(dms.authors.labels(), 0, month_label(start), dataset_sum, options.integer("width")).with_colors(dms.authors.colors());
+        for (idx, counts) in started.iter().enumerate() {
+            for (author, count) in counts.iter().copied().enumerate().filter(|(_, count)| *count > 0) {
+                graph.add(author, idx, BasisPoints::of(count, counts.iter().sum()));
+            }
+        }
+        report.push(graph.finish("Share of Conversations Started per Month"));
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde_structs::tests::export;
+
+    #[test]
+    fn sessionize_splits_at_gaps() {
+        let dms: DirectMessages = export(1, &[
+            (1, 1, "2023-01-01T10:00:00"),
+            (2, 2, "2023-01-01T10:20:00"),
+            (3, 1, "2023-01-01T10:30:00"),
+            // 90 minutes of silence
+            (4, 2, "2023-01-01T12:00:00"),
+            (5, 2, "2023-01-01T12:59:00"),
+            // exactly the gap still continues the session
+            (6, 1, "2023-01-01T13:59:00"),
+        ]).try_into().unwrap();
+        let sessions = sessionize(&dms, TimeDelta::hours(1), false);
+        let summary = sessions.iter().map(|session| (session.first_id, session.last_id, dms.authors[session.starter].id, dms.authors[session.closer].id, session.messages, session.length().num_minutes())).collect::<Vec<_>>();
+        assert_eq!(summary, [(1, 3, 1, 1, 3, 30), (4, 6, 2, 1, 3, 119)]);
+    }
+
+    #[test]
+    fn sessionize_single_message() {
+        let dms: DirectMessages = export(1, &[(1, 1, "2023-01-01T10:00:00")]).try_into().unwrap();
+        let sessions = sessionize(&dms, TimeDelta::hours(1), true);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].messages, sessions[0].calls, sessions[0].length()), (1, 0, TimeDelta::zero()));
+    }
+
+    #[test]
+    fn sessionize_nothing() {
+        let dms: DirectMessages = export(1, &[]).try_into().unwrap();
+        assert!(sessionize(&dms, TimeDelta::hours(1), true).is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::render::parse_color;
+use crate::filter::parse_since;
+use crate::time::{ZonePeriod, ZoneSchedule};
+use anyhow::{Context, Result};
+use chrono::TimeDelta;
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub people: Vec<Person>,
+}
+
+impl Config {
+    pub fn read(path: &Path) -> Result<Self> {
+        serde_json::from_slice(&std::fs::read(path)?).with_context(|| format!("Failed to parse config '{}'", path.display()))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Person {
+    pub name: String,
+    #[serde(deserialize_with = "ids")]
+    pub ids: Vec<u64>,
+    #[serde(default, deserialize_with = "color")]
+    pub color: Option<[u8; 3]>,
+    #[serde(default, deserialize_with = "timezone")]
+    pub timezone: Option<ZoneSchedule>,
+}
+
+fn ids<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        Text(String),
+    }
+
+    Vec::<Id>::deserialize(deserializer)?.into_iter().map(|id| match id {
+        Id::Number(id) => Ok(id),
+        Id::Text(id) => id.parse().map_err(|_| serde::de::Error::custom(format!("Expected a user id, found '{id}'"))),
+    }).collect()
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[u8; 3]>, D::Error> {
+    let Some(color) = Option::<String>::deserialize(deserializer)? else { return Ok(None) };
+    parse_color(&color).ok().and_then(|color| color.try_into().ok()).map(Some).ok_or_else(|| serde::de::Error::custom(format!("Expected a colour in the form RRGGBB, found '{color}'")))
+}
+
+fn timezone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ZoneSchedule>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timezone {
+        Single(String),
+        Periods(Vec<Period>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Period {
+        zone: String,
+        since: Option<String>,
+    }
+
+    let periods = match Option::<Timezone>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Timezone::Single(zone)) => vec![Period { zone, since: None }],
+        Some(Timezone::Periods(periods)) => periods,
+    };
+    let periods = periods.into_iter().map(|Period { zone, since }| Ok(ZonePeriod { zone: zone.parse()?, since: since.as_deref().map(parse_since).transpose()?.map(|since| since.at(TimeDelta::zero())) })).collect::<Result<Vec<_>>>();
+    periods.and_then(ZoneSchedule::new).map(Some).map_err(serde::de::Error::custom)
+}
@@ -1,10 +1,15 @@
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter;
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign};
-use chrono::TimeDelta;
+use std::str::FromStr;
+use anyhow::{bail, Context};
+use chrono::{Datelike, NaiveDate, TimeDelta};
 use itertools::Itertools;
-use crate::{generate_progress_bar, standard_deviation};
+use num_format::{Locale, ToFormattedString};
+use serde::{Serialize, Serializer};
+use crate::{generate_progress_bar, percentile, standard_deviation};
 
 #[derive(Copy, Clone, Default)]
 pub struct TimeQuantity {
@@ -18,6 +23,12 @@ pub struct TimeQuantity {
 impl TimeQuantity {
     pub const ZERO: Self = Self::new(0, 0, 0, 0, 0);
 
+    pub fn short(&self) -> String {
+        let Self { days, hours, minutes, seconds, milliseconds } = *self;
+        let buf = [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s"), (milliseconds, "ms")].into_iter().filter(|(quantity, _)| *quantity > 0).map(|(quantity, unit)| format!("{quantity}{unit}")).collect::<String>();
+        if buf.is_empty() { "0s".to_owned() } else { buf }
+    }
+
     pub const fn new(days: usize, hours: usize, minutes: usize, seconds: usize, milliseconds: usize) -> Self {
         Self {
             days,
@@ -66,6 +77,12 @@ impl From<TimeQuantity> for usize {
     }
 }
 
+impl From<TimeQuantity> for TimeDelta {
+    fn from(time: TimeQuantity) -> Self {
+        TimeDelta::milliseconds(usize::from(time) as i64)
+    }
+}
+
 impl Add for TimeQuantity {
     type Output = Self;
 
@@ -76,7 +93,7 @@ impl Add for TimeQuantity {
 
 impl AddAssign for TimeQuantity {
     fn add_assign(&mut self, rhs: Self) {
-        *self = self.clone() + rhs;
+        *self = *self + rhs;
     }
 }
 
@@ -90,7 +107,7 @@ impl Div<usize> for TimeQuantity {
 
 impl DivAssign<usize> for TimeQuantity {
     fn div_assign(&mut self, rhs: usize) {
-        *self = self.clone() / rhs;
+        *self = *self / rhs;
     }
 }
 
@@ -104,7 +121,7 @@ impl Mul<usize> for TimeQuantity {
 
 impl MulAssign<usize> for TimeQuantity {
     fn mul_assign(&mut self, rhs: usize) {
-        *self = self.clone() * rhs;
+        *self = *self * rhs;
     }
 }
 
@@ -131,6 +148,43 @@ impl Display for TimeQuantity {
     }
 }
 
+impl FromStr for TimeQuantity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut ms = 0_usize;
+        let mut rest = s.trim();
+        if rest.is_empty() {
+            bail!("Expected a duration such as '15s', '1h30m' or '90d'");
+        }
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let quantity = rest[..digits].parse::<usize>().with_context(|| format!("Expected a number in duration '{s}'"))?;
+            rest = &rest[digits..];
+            let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+            let scale = match &rest[..unit] {
+                "w" => 1000 * 60 * 60 * 24 * 7,
+                "d" => 1000 * 60 * 60 * 24,
+                "h" => 1000 * 60 * 60,
+                "m" => 1000 * 60,
+                "s" => 1000,
+                "ms" => 1,
+                unit => bail!("Unknown unit '{unit}' in duration '{s}'"),
+            };
+            // kept within what a `TimeDelta` can hold
+            ms = quantity.checked_mul(scale).and_then(|part| ms.checked_add(part)).filter(|ms| *ms <= i64::MAX as usize).with_context(|| format!("Duration out of range in '{s}'"))?;
+            rest = &rest[unit..];
+        }
+        Ok(Self::from(ms))
+    }
+}
+
+impl Serialize for TimeQuantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(usize::from(*self) as u64)
+    }
+}
+
 impl Debug for TimeQuantity {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let Self { days, hours, minutes, seconds, milliseconds } = *self;
@@ -138,9 +192,71 @@ impl Debug for TimeQuantity {
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Count,
+    Milliseconds,
+    BasisPoints,
+}
+
+impl Unit {
+    pub fn format(self, value: usize) -> String {
+        match self {
+            Unit::Count => value.to_formatted_string(&Locale::en),
+            Unit::Milliseconds => TimeQuantity::from(value).short(),
+            Unit::BasisPoints => format!("{pct:.2}%", pct = value as f64 / 100.0),
+        }
+    }
+}
+
+pub trait Quantity: From<usize> {
+    const UNIT: Unit;
+}
+
+impl Quantity for usize {
+    const UNIT: Unit = Unit::Count;
+}
+
+impl Quantity for TimeQuantity {
+    const UNIT: Unit = Unit::Milliseconds;
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct BasisPoints(pub usize);
+
+impl BasisPoints {
+    pub fn of(part: usize, whole: usize) -> Self {
+        Self((part * 10_000).checked_div(whole).unwrap_or(0))
+    }
+}
+
+impl From<usize> for BasisPoints {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BasisPoints> for usize {
+    fn from(value: BasisPoints) -> Self {
+        value.0
+    }
+}
+
+impl Sum for BasisPoints {
+    fn sum<I: Iterator<Item=Self>>(iter: I) -> Self {
+        Self(iter.map(|x| x.0).sum())
+    }
+}
+
+impl Quantity for BasisPoints {
+    const UNIT: Unit = Unit::BasisPoints;
+}
+
 pub struct Graph<'a, T: From<usize>, S: Fn(&[T]) -> usize, F: Fn(usize) -> String> {
     labels: Vec<String>,
-    authors: Box<[&'a str]>,
+    series: Box<[&'a str]>,
+    colors: Vec<Option<[u8; 3]>>,
     data: Vec<Box<[Vec<T>]>>,
     start_idx: usize,
     width: usize,
@@ -149,59 +265,207 @@ pub struct Graph<'a, T: From<usize>, S: Fn(&[T]) -> usize, F: Fn(usize) -> Strin
 }
 
 impl<'a, T: From<usize>, S: Fn(&[T]) -> usize, F: Fn(usize) -> String> Graph<'a, T, S, F> {
-    pub fn new(authors: impl Into<Box<[&'a str]>>, start_idx: usize, label_fn: F, sum: S, width: usize) -> Self {
-        let authors = authors.into();
+    pub fn new(series: impl Into<Box<[&'a str]>>, start_idx: usize, label_fn: F, sum: S, width: usize) -> Self {
+        let series = series.into();
         Self {
             labels: Vec::new(),
             data: Vec::new(),
             start_idx,
-            authors,
+            series,
+            colors: Vec::new(),
             width,
             sum,
             label_fn,
         }
     }
 
-    pub fn add(&mut self, author: &str, idx: usize, quantity: T) -> bool {
-        let Some(author_index) = self.authors.iter().position(|x| *x == author) else { return false };
-        if self.data.len() <= idx {
-            self.data.extend(iter::from_fn(|| Some(Box::<[Vec<T>]>::from_iter(iter::from_fn(|| Some(Vec::new())).take(self.authors.len())))).take(idx + 1 - self.data.len()));
+    pub fn with_colors(self, colors: Vec<Option<[u8; 3]>>) -> Self {
+        Self { colors, ..self }
+    }
+
+    // shows at least `len` buckets, even if the last ones stay empty
+    pub fn with_buckets(mut self, len: usize) -> Self {
+        self.grow(len);
+        self
+    }
+
+    fn grow(&mut self, len: usize) {
+        if self.data.len() < len {
+            self.data.extend(iter::from_fn(|| Some(Box::<[Vec<T>]>::from_iter(iter::from_fn(|| Some(Vec::new())).take(self.series.len())))).take(len - self.data.len()));
             let mut label_idx = self.labels.len();
             self.labels.extend(iter::from_fn(|| {
                 let label = (self.label_fn)(label_idx);
                 label_idx += 1;
                 Some(label)
-            }).take(idx + 1 - self.labels.len()));
+            }).take(len - self.labels.len()));
+        }
+    }
+
+    pub fn add(&mut self, series: usize, idx: usize, quantity: T) -> bool {
+        if series >= self.series.len() {
+            return false
         }
+        self.grow(idx + 1);
         let Some(line) = self.data.get_mut(idx) else { return false };
-        line[author_index].push(quantity);
+        line[series].push(quantity);
         true
     }
+
+    pub fn finish(&self, title: impl Into<String>) -> GraphData where T: Quantity {
+        // buckets before `start_idx` that nothing was added to are shown empty
+        let order = || (self.start_idx..self.data.len().max(self.start_idx)).chain(0..self.start_idx);
+        GraphData {
+            title: title.into(),
+            series: self.series.iter().map(|series| series.to_string()).collect(),
+            colors: self.colors.clone(),
+            labels: order().map(|idx| self.labels.get(idx).cloned().unwrap_or_else(|| (self.label_fn)(idx))).collect(),
+            values: order().map(|idx| self.data.get(idx).map_or_else(|| vec![0; self.series.len()], |line| line.iter().map(|quantities| (self.sum)(quantities)).collect())).collect(),
+            unit: T::UNIT,
+            width: self.width,
+        }
+    }
 }
 
-impl<T: From<usize>, S: Fn(&[T]) -> usize, F: Fn(usize) -> String> Display for Graph<'_, T, S, F> {
+pub const SERIES_COLORS: [[u8; 3]; 5] = [[0x98, 0xC3, 0x79], [0xE5, 0xC0, 0x7B], [0x5E, 0xAC, 0xEC], [0xC6, 0x78, 0xDD], [0x56, 0xB6, 0xC2]];
+
+#[derive(Serialize)]
+pub struct GraphData {
+    pub title: String,
+    pub series: Vec<String>,
+    pub colors: Vec<Option<[u8; 3]>>,
+    pub labels: Vec<String>,
+    pub values: Vec<Vec<usize>>,
+    pub unit: Unit,
+    pub width: usize,
+}
+
+impl GraphData {
+    pub fn totals(&self) -> impl Iterator<Item=usize> + '_ {
+        self.values.iter().map(|line| line.iter().sum::<usize>())
+    }
+
+    pub fn format_value(&self, value: usize) -> String {
+        self.unit.format(value)
+    }
+}
+
+impl Display for GraphData {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         const FULL_CHAR: char = '#';
         const EMPTY_CHAR: char = '-';
 
-        let (min, max) = self.data.iter().map(|line| line.iter().map(|u| (&self.sum)(&u)).sum::<usize>()).minmax().into_option().unwrap_or((0, 0));
-        let sum = self.data.iter().map(|line| line.iter().map(|u| (&self.sum)(&u)).sum::<usize>()).sum::<usize>();
-        let mean = sum as f64 / self.data.len() as f64;
-        let sd = standard_deviation(sum, self.data.iter().map(|line| line.iter().map(|u| (&self.sum)(&u)).sum::<usize>()), self.data.len());
+        let (min, max) = self.totals().minmax().into_option().unwrap_or((0, 0));
+        let sum = self.totals().sum::<usize>();
+        let mean = sum as f64 / self.values.len() as f64;
+        let sd = standard_deviation(sum, self.totals(), self.values.len());
 
         writeln!(f, "Graph Data: sum = {sum}, mean = {mean}, sd = {sd}, width = {width}, min = {min}, max = {max}", width = self.width)?;
         writeln!(f, "Legend:")?;
-        for (idx, author) in self.authors.iter().enumerate() {
-            writeln!(f, "\x1B[{color}m{author}\x1B[0m", color = 92 + idx % 5)?
+        for (idx, series) in self.series.iter().enumerate() {
+            writeln!(f, "\x1B[{color}m{series}\x1B[0m", color = 92 + idx % 5)?
         }
-        for (idx, quantities) in (self.start_idx..self.data.len()).chain(0..self.start_idx).map(|idx| (idx, &self.data[idx])) {
-            writeln!(f, "{label} | {bar}", label = self.labels[idx], bar = generate_progress_bar(self.width, FULL_CHAR, EMPTY_CHAR, max, &quantities, |vec| (&self.sum)(&vec)))?;
+        for (label, quantities) in self.labels.iter().zip(&self.values) {
+            writeln!(f, "{label} | {bar}", bar = generate_progress_bar(self.width, FULL_CHAR, EMPTY_CHAR, max, quantities, |quantity| *quantity))?;
         }
 
         Ok(())
     }
 }
 
+// from no activity to the busiest days, in the style of GitHub's contribution graph
+pub const CALENDAR_COLORS: [[u8; 3]; 5] = [[0x4E, 0x50, 0x58], [0x0E, 0x44, 0x29], [0x00, 0x6D, 0x32], [0x26, 0xA6, 0x41], [0x39, 0xD3, 0x53]];
+
+#[derive(Serialize)]
+pub struct CalendarData {
+    pub title: String,
+    pub days: BTreeMap<NaiveDate, usize>,
+    pub unit: Unit,
+    #[serde(skip)]
+    pub truecolor: bool,
+}
+
+impl CalendarData {
+    pub fn years(&self) -> impl Iterator<Item=i32> {
+        let first = self.days.keys().next().map_or(0, NaiveDate::year);
+        let last = self.days.keys().next_back().map_or(-1, NaiveDate::year);
+        first..=last
+    }
+
+    pub fn max(&self) -> usize {
+        self.days.values().copied().max().unwrap_or(0)
+    }
+
+    pub fn value(&self, date: NaiveDate) -> usize {
+        self.days.get(&date).copied().unwrap_or(0)
+    }
+
+    // 0 for days without activity, otherwise the quarter of the busiest day this one falls in
+    pub fn level(&self, date: NaiveDate, max: usize) -> usize {
+        match self.value(date) {
+            0 => 0,
+            value => (value * 4).div_ceil(max).clamp(1, 4),
+        }
+    }
+
+    pub fn format_value(&self, value: usize) -> String {
+        self.unit.format(value)
+    }
+
+    // every day of the year as (week column, weekday row, date), weeks start on Monday
+    pub fn cells(year: i32) -> impl Iterator<Item=(usize, usize, NaiveDate)> {
+        let first = NaiveDate::from_yo_opt(year, 1).expect("Valid year");
+        let offset = first.weekday().num_days_from_monday() as usize;
+        first.iter_days().take_while(move |date| date.year() == year).map(move |date| {
+            let idx = date.ordinal0() as usize + offset;
+            (idx / 7, idx % 7, date)
+        })
+    }
+
+    pub fn weeks(year: i32) -> usize {
+        Self::cells(year).last().map_or(0, |(week, _, _)| week + 1)
+    }
+}
+
+impl Display for CalendarData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        const BLOCK: char = '\u{25A0}';
+        const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+        let paint = |[r, g, b]: [u8; 3]| if self.truecolor {
+            format!("\x1B[38;2;{r};{g};{b}m{BLOCK}\x1B[0m")
+        } else {
+            let scale = |c: u8| (c as usize * 5 + 127) / 255;
+            format!("\x1B[38;5;{n}m{BLOCK}\x1B[0m", n = 16 + 36 * scale(r) + 6 * scale(g) + scale(b))
+        };
+
+        let max = self.max();
+        writeln!(f, "Calendar Data: days = {days}, total = {total}, max = {max}", days = self.days.len(), total = self.format_value(self.days.values().sum()), max = self.format_value(max))?;
+        for year in self.years() {
+            let mut grid = vec![[None; 7]; Self::weeks(year)];
+            for (week, weekday, date) in Self::cells(year) {
+                grid[week][weekday] = Some(date);
+            }
+
+            let mut months = " ".repeat(4 + grid.len() * 2);
+            for (week, _, date) in Self::cells(year).filter(|(_, _, date)| date.day() == 1) {
+                months.replace_range(4 + week * 2..4 + week * 2 + 3, &date.format("%b").to_string());
+            }
+            writeln!(f, "\n{year}\n{months}", months = months.trim_end())?;
+            for (weekday, label) in WEEKDAYS.iter().enumerate() {
+                write!(f, "{label} ")?;
+                for week in &grid {
+                    match week[weekday] {
+                        Some(date) => write!(f, "{block} ", block = paint(CALENDAR_COLORS[self.level(date, max)]))?,
+                        None => write!(f, "  ")?,
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "\nLess {blocks} More", blocks = CALENDAR_COLORS.iter().map(|color| paint(*color)).join(" "))
+    }
+}
+
 pub fn dataset_sum<T: Sum + Into<usize> + Clone>(data: &[T]) -> usize {
     data.iter().cloned().sum::<T>().into()
 }
@@ -209,3 +473,44 @@ pub fn dataset_sum<T: Sum + Into<usize> + Clone>(data: &[T]) -> usize {
 pub fn dataset_average<T: Sum + Div<usize> + Into<usize> + Clone>(data: &[T]) -> usize where <T as Div<usize>>::Output: Into<usize> {
     (data.iter().cloned().sum::<T>() / data.len()).into()
 }
+
+pub fn dataset_median<T: Into<usize> + Clone>(data: &[T]) -> usize {
+    percentile(&data.iter().cloned().map(Into::into).sorted().collect::<Vec<usize>>(), 50).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(s: &str) -> usize {
+        s.parse::<TimeQuantity>().unwrap().into()
+    }
+
+    #[test]
+    fn time_quantity_from_str() {
+        assert_eq!(ms("15s"), 15_000);
+        assert_eq!(ms("1h30m"), 90 * 60 * 1000);
+        assert_eq!(ms(" 2w1d "), 15 * 24 * 60 * 60 * 1000);
+        assert_eq!(ms("1m500ms"), 60_500);
+        assert_eq!(ms("0s"), 0);
+    }
+
+    #[test]
+    fn time_quantity_from_str_rejects_invalid() {
+        for s in ["", "15", "h", "1y", "1.5h", "-1h", "9999999999999d", "99999999999999999999ms", "9223372036854775807ms1ms"] {
+            assert!(s.parse::<TimeQuantity>().is_err(), "'{s}' should not parse");
+        }
+    }
+
+    #[test]
+    fn graph_with_buckets_keeps_empty_buckets_out_of_the_median() {
+        let mut graph = Graph::new(["a"], 0, |idx| format!("{idx:02}h"), dataset_median, 10).with_buckets(24);
+        graph.add(0, 3, 5_usize);
+        graph.add(0, 3, 7_usize);
+        let data = graph.finish("test");
+        assert_eq!(data.labels.len(), 24);
+        assert_eq!(data.labels[23], "23h");
+        assert_eq!(data.values[3], [5]);
+        assert_eq!(data.values[23], [0]);
+    }
+}
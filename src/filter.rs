@@ -0,0 +1,169 @@
+use crate::data::TimeQuantity;
+use crate::serde_structs::Author;
+use anyhow::{bail, Context, Result};
+use chrono::{Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use std::fmt::{Display, Formatter};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub struct DateRange {
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+impl DateRange {
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    pub fn contains(&self, timestamp: NaiveDateTime) -> bool {
+        self.since.is_none_or(|since| timestamp >= since) && self.until.is_none_or(|until| timestamp < until)
+    }
+
+    // the smallest range containing all of `ranges`
+    pub fn hull(ranges: &[DateRange]) -> DateRange {
+        DateRange {
+            since: ranges.iter().map(|range| range.since).collect::<Option<Vec<_>>>().and_then(|sinces| sinces.into_iter().min()),
+            until: ranges.iter().map(|range| range.until).collect::<Option<Vec<_>>>().and_then(|untils| untils.into_iter().max()),
+        }
+    }
+}
+
+impl Display for DateRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.since, self.until) {
+            (Some(since), Some(until)) => write!(f, "Messages from {since} until {until}"),
+            (Some(since), None) => write!(f, "Messages from {since}"),
+            (None, Some(until)) => write!(f, "Messages until {until}"),
+            (None, None) => write!(f, "All messages"),
+        }
+    }
+}
+
+// a plain date moves with `--day-start`, an exact time does not
+#[derive(Copy, Clone)]
+pub enum Bound {
+    Date(NaiveDate),
+    Time(NaiveDateTime),
+}
+
+impl Bound {
+    pub fn at(self, day_start: TimeDelta) -> NaiveDateTime {
+        match self {
+            Bound::Date(date) => date.and_time(NaiveTime::MIN) + day_start,
+            Bound::Time(timestamp) => timestamp,
+        }
+    }
+}
+
+pub fn parse_since(s: &str) -> Result<Bound> {
+    parse_bound(s, false)
+}
+
+pub fn parse_until(s: &str) -> Result<Bound> {
+    parse_bound(s, true)
+}
+
+// a plain date used as an upper bound includes that whole day
+fn parse_bound(s: &str, inclusive_date: bool) -> Result<Bound> {
+    let s = s.trim();
+    let today = Local::now().date_naive();
+    let date = match s.to_ascii_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.checked_sub_days(Days::new(1)),
+        _ => NaiveDate::parse_from_str(s, "%Y-%m-%d").ok(),
+    };
+    if let Some(date) = date {
+        let date = if inclusive_date { date.checked_add_days(Days::new(1)).context("Date out of range")? } else { date };
+        return Ok(Bound::Date(date))
+    }
+    if let Some(timestamp) = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"].iter().find_map(|format| NaiveDateTime::parse_from_str(s, format).ok()) {
+        return Ok(Bound::Time(timestamp))
+    }
+    parse_relative(s).map(Bound::Time).with_context(|| format!("Expected a date such as '2023-01-31', '2023-01-31 18:00', 'yesterday', 'last 90 days' or '6w', found '{s}'"))
+}
+
+fn parse_relative(s: &str) -> Result<NaiveDateTime> {
+    let now = Local::now().naive_local();
+    let lower = s.to_ascii_lowercase();
+    let relative = lower.strip_prefix("last ").or_else(|| lower.strip_suffix(" ago")).unwrap_or(&lower).trim();
+    let Some((quantity, unit)) = relative.split_once(' ') else {
+        return now.checked_sub_signed(TimeDelta::from(relative.parse::<TimeQuantity>()?)).context("Date out of range")
+    };
+    let quantity = quantity.parse::<u32>().with_context(|| format!("Expected a number, found '{quantity}'"))?;
+    match unit.trim_end_matches('s') {
+        "hour" => TimeDelta::try_hours(quantity as i64).and_then(|delta| now.checked_sub_signed(delta)),
+        "day" => TimeDelta::try_days(quantity as i64).and_then(|delta| now.checked_sub_signed(delta)),
+        "week" => TimeDelta::try_weeks(quantity as i64).and_then(|delta| now.checked_sub_signed(delta)),
+        "month" => now.checked_sub_months(Months::new(quantity)),
+        "year" => quantity.checked_mul(12).and_then(|months| now.checked_sub_months(Months::new(months))),
+        unit => bail!("Unknown unit '{unit}'"),
+    }.context("Date out of range")
+}
+
+pub struct AuthorFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl AuthorFilter {
+    pub fn keeps(&self, author: &Author) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| matches(pattern, author))) && !self.exclude.iter().any(|pattern| matches(pattern, author))
+    }
+
+    pub fn unmatched<'a>(&'a self, authors: &'a [&Author]) -> impl Iterator<Item=&'a str> {
+        self.include.iter().chain(&self.exclude).filter(|pattern| !authors.iter().any(|author| matches(pattern, author))).map(String::as_str)
+    }
+}
+
+fn matches(pattern: &str, author: &Author) -> bool {
+    pattern.trim().parse::<u64>().is_ok_and(|id| id == author.id) || pattern.trim().eq_ignore_ascii_case(&author.label) || author.history.iter().any(|record| pattern.trim().eq_ignore_ascii_case(&record.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(time.0, time.1, 0).unwrap()
+    }
+
+    fn since(s: &str) -> NaiveDateTime {
+        parse_since(s).unwrap().at(TimeDelta::zero())
+    }
+
+    fn until(s: &str) -> NaiveDateTime {
+        parse_until(s).unwrap().at(TimeDelta::zero())
+    }
+
+    #[test]
+    fn plain_dates() {
+        assert_eq!(since("2023-01-31"), at((2023, 1, 31), (0, 0)));
+        // an upper bound includes the whole day
+        assert_eq!(until("2023-01-31"), at((2023, 2, 1), (0, 0)));
+        assert_eq!(until("2023-12-31"), at((2024, 1, 1), (0, 0)));
+        assert_eq!(since("today"), Local::now().date_naive().and_time(NaiveTime::MIN));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(since("2023-01-31 18:00"), at((2023, 1, 31), (18, 0)));
+        assert_eq!(until("2023-01-31 18:00"), at((2023, 1, 31), (18, 0)));
+        assert_eq!(since("2023-01-31T18:30:00"), at((2023, 1, 31), (18, 30)));
+    }
+
+    #[test]
+    fn relative() {
+        let close_to = |parsed: NaiveDateTime, ago: TimeDelta| (Local::now().naive_local() - ago - parsed).abs() < TimeDelta::minutes(1);
+        assert!(close_to(since("6w"), TimeDelta::weeks(6)));
+        assert!(close_to(since("last 90 days"), TimeDelta::days(90)));
+        assert!(close_to(since("3 hours ago"), TimeDelta::hours(3)));
+        assert!(close_to(until("1 day"), TimeDelta::days(1)));
+    }
+
+    #[test]
+    fn invalid() {
+        for s in ["", "2023-13-01", "last 3 fortnights", "soon", "1000000000d", "last 4000000000 years", "4000000000 weeks ago"] {
+            assert!(parse_since(s).is_err(), "'{s}' should not parse");
+        }
+    }
+}
@@ -2,67 +2,356 @@
 #![allow(dead_code)]
 
 #![feature(generic_const_exprs)]
-#![feature(let_chains)]
 
+pub mod analysis;
+pub mod config;
 pub mod data;
+pub mod filter;
+pub mod render;
+pub mod report;
 pub mod serde_structs;
-
-use crate::data::{dataset_average, dataset_sum, Graph, TimeQuantity};
-use crate::serde_structs::{Call, DirectMessages, Message, UninitDirectMessages};
+pub mod time;
+
+use crate::analysis::comparison::COMPARISONS;
+use crate::analysis::{Analysis, AnalysisOptions, OptionSpec, ANALYSES};
+use crate::config::Config;
+use crate::data::TimeQuantity;
+use crate::filter::{parse_since, parse_until, AuthorFilter, Bound, DateRange};
+use crate::report::{Report, Section};
+use crate::render::{parse_color, ChartStyle, Document, DocumentChannel, Format};
+use crate::serde_structs::{AuthorLabel, DirectMessages, UninitDirectMessages};
+use crate::time::{parse_day_start, TimezoneSetting};
 use anyhow::{Context, Result};
-use chrono::{Datelike, Days, NaiveDate, TimeDelta, Timelike, Weekday};
 use crossterm::cursor::{MoveTo, MoveToNextLine};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::style::{Color, Colors, Print, SetColors};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
 use crossterm::{event, execute};
-use fxhash::FxHashMap;
-use image::{ImageFormat, Pixel, Rgba};
+use chrono::TimeDelta;
 use itertools::Itertools;
-use num_format::{Locale, ToFormattedString};
-use num_traits::{FromPrimitive, Pow};
+use num_traits::Pow;
 use std::fmt::Write;
-use std::fs::File;
 use std::io::stdout;
-use std::path::Path;
+use std::path::PathBuf;
 use std::time::Instant;
 use clipboard_rs::Clipboard;
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+
+/// Analyses DiscordChatExporter JSON exports of DMs and group chats.
+///
+/// Without `--analysis` or `--all` an interactive menu is shown to pick the analyses to run.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// The DiscordChatExporter JSON exports to analyse, or directories containing them; exports of one channel are merged
+    #[arg(required_unless_present = "list")]
+    paths: Vec<PathBuf>,
+    /// Comma-separated ids of the analyses to run, see `--list`
+    #[arg(short, long, value_delimiter = ',', value_parser = PossibleValuesParser::new(ANALYSES.iter().map(|analysis| analysis.id())))]
+    analysis: Vec<String>,
+    /// Run every analysis
+    #[arg(long, conflicts_with = "analysis")]
+    all: bool,
+    /// Compare the channels of the given exports side by side, running all comparisons or only those given as `--compare=a,b`
+    #[arg(long, num_args = 0..=1, require_equals = true, value_delimiter = ',', value_name = "COMPARISONS", conflicts_with_all = ["analysis", "all"], value_parser = PossibleValuesParser::new(COMPARISONS.iter().map(|comparison| comparison.id())))]
+    compare: Option<Vec<String>>,
+    /// List the available analyses and their options and exit
+    #[arg(long)]
+    list: bool,
+    /// Only analyse messages sent at or after this date, e.g. `2023-01-31`, `2023-01-31 18:00`, `last 90 days` or `6w`
+    #[arg(long, value_parser = parse_since)]
+    since: Option<Bound>,
+    /// Only analyse messages sent before this date, a plain date includes that whole day
+    #[arg(long, value_parser = parse_until)]
+    until: Option<Bound>,
+    /// Set an analysis or comparison option, e.g. `top-call-lengths.limit=50` or `text-time-of-day.since=last 1 year`
+    #[arg(short, long = "set", value_name = "ANALYSIS.OPTION=VALUE", value_parser = parse_option_override)]
+    set: Vec<(String, String, String)>,
+    /// Which name to label authors with
+    #[arg(long, value_enum, default_value_t = AuthorLabel::Name)]
+    author_label: AuthorLabel,
+    /// Label the author with the given user id with a custom name, e.g. `123456789012345678=Alice`
+    #[arg(long, value_name = "ID=NAME", value_parser = parse_alias)]
+    alias: Vec<(u64, String)>,
+    /// Time zone to bucket hours and days in: `local`, an IANA name such as `Europe/Berlin`, an offset such as `+02:00`, or `per-author` to use each person's `timezone` from `--config`
+    #[arg(long, default_value = "local")]
+    timezone: TimezoneSetting,
+    /// Time of day at which days start for daily, weekly and calendar buckets, e.g. `05:00` to count late nights towards the day before
+    #[arg(long, default_value = "00:00", value_parser = parse_day_start)]
+    day_start: TimeDelta,
+    /// Only analyse these authors, given as comma-separated user ids, labels or usernames
+    #[arg(long, value_delimiter = ',')]
+    include_author: Vec<String>,
+    /// Leave out these authors, given as comma-separated user ids, labels or usernames
+    #[arg(long, value_delimiter = ',')]
+    exclude_author: Vec<String>,
+    /// Keep the messages of filtered out authors as one "Everyone else" author for group-wide context
+    #[arg(long)]
+    group_totals: bool,
+    /// JSON config file merging several user ids into one person, e.g. `{"people": [{"name": "Bob", "ids": ["123", "456"], "color": "E5C07B"}]}`
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// The format to write the analysis in
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Where to write the analysis, defaults to `discord_dm_analysis` with the format's extension
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Width of exported charts in pixels, 960 unless an analysis writing its own image picks a size
+    #[arg(long)]
+    chart_width: Option<u32>,
+    /// Height of exported charts in pixels, 400 unless an analysis writing its own image picks a size
+    #[arg(long)]
+    chart_height: Option<u32>,
+    /// Comma-separated RRGGBB colours to use for the series of exported charts
+    #[arg(long, value_delimiter = ',', value_parser = parse_rgb)]
+    chart_colors: Vec<[u8; 3]>,
+    /// RRGGBB or RRGGBBAA background colour of exported charts
+    #[arg(long, value_parser = parse_rgba)]
+    chart_background: Option<[u8; 4]>,
+}
+
+const WINDOW_KEYS: [&str; 2] = ["since", "until"];
+
+impl Args {
+    fn is_interactive(&self) -> bool {
+        self.analysis.is_empty() && !self.all && self.compare.is_none()
+    }
+
+    fn options_for(&self, id: &str, specs: &[OptionSpec]) -> Result<AnalysisOptions> {
+        let options = AnalysisOptions::new(id, specs, self.set.iter().filter(|(set_id, key, _)| set_id == id && !WINDOW_KEYS.contains(&key.as_str())).map(|(_, key, value)| (key.as_str(), value.as_str())))?;
+        Ok(options.with_style(self.chart_style(), self.chart_width.is_some() || self.chart_height.is_some()))
+    }
+
+    fn chart_style(&self) -> ChartStyle {
+        let default = ChartStyle::default();
+        ChartStyle {
+            width: self.chart_width.unwrap_or(default.width),
+            height: self.chart_height.unwrap_or(default.height),
+            colors: self.chart_colors.clone(),
+            background: self.chart_background,
+        }
+    }
+
+    fn window(&self) -> DateRange {
+        DateRange { since: self.since.map(|since| since.at(self.day_start)), until: self.until.map(|until| until.at(self.day_start)) }
+    }
+
+    fn window_for(&self, id: &str) -> Result<DateRange> {
+        let mut window = self.window();
+        for (_, key, value) in self.set.iter().filter(|(set_id, _, _)| set_id == id) {
+            match key.as_str() {
+                "since" => window.since = Some(parse_since(value).with_context(|| format!("Invalid value for '{id}.since'"))?.at(self.day_start)),
+                "until" => window.until = Some(parse_until(value).with_context(|| format!("Invalid value for '{id}.until'"))?.at(self.day_start)),
+                _ => {},
+            }
+        }
+        Ok(window)
+    }
+}
+
+fn parse_rgb(s: &str) -> Result<[u8; 3]> {
+    parse_color(s)?.try_into().ok().with_context(|| format!("Expected a colour in the form RRGGBB, found '{s}'"))
+}
+
+fn parse_rgba(s: &str) -> Result<[u8; 4]> {
+    let color = parse_color(s)?;
+    Ok([color[0], color[1], color[2], color.get(3).copied().unwrap_or(0xFF)])
+}
+
+fn parse_alias(s: &str) -> Result<(u64, String)> {
+    let (id, name) = s.split_once('=').context("Expected ID=NAME")?;
+    Ok((id.trim().parse().with_context(|| format!("Expected a user id, found '{id}'"))?, name.to_owned()))
+}
+
+fn parse_option_override(s: &str) -> Result<(String, String, String)> {
+    let (path, value) = s.split_once('=').context("Expected ANALYSIS.OPTION=VALUE")?;
+    let (id, key) = path.split_once('.').context("Expected ANALYSIS.OPTION=VALUE")?;
+    anyhow::ensure!(analysis::find(id).is_some() || analysis::comparison::find(id).is_some(), "Unknown analysis '{id}'");
+    Ok((id.to_owned(), key.to_owned(), value.to_owned()))
+}
 
 fn main() -> Result<()> {
-    let Some(path) = std::env::args().nth(1) else {
-        println!("No path specified; usage: discord_dm_analyser <file>");
-        std::process::exit(0);
-    };
+    let args = Args::parse();
+
+    if args.list {
+        for analysis in ANALYSES {
+            println!("{id:<40} {description}", id = analysis.id(), description = analysis.description());
+            for option in analysis.options() {
+                println!("    {key:<36} {description} (default: {default})", key = option.key, description = option.description, default = option.default);
+            }
+        }
+        println!("\nEvery analysis and comparison also accepts the options `since` and `until`, overriding `--since` and `--until`");
+        println!("\nComparisons (--compare):");
+        for comparison in COMPARISONS {
+            println!("{id:<40} {description}", id = comparison.id(), description = comparison.description());
+            for option in comparison.options() {
+                println!("    {key:<36} {description} (default: {default})", key = option.key, description = option.description, default = option.default);
+            }
+        }
+        return Ok(())
+    }
 
-    parse_dms(&path).context("Failed to evalulate DM information")
+    parse_dms(&args).context("Failed to evalulate DM information")
 }
 
-fn parse_dms<P: AsRef<Path>>(path: P) -> Result<()> {
+fn parse_dms(args: &Args) -> Result<()> {
+    let paths = collect_export_paths(&args.paths)?;
+
     println!("Parsing DMs...");
     let start = Instant::now();
-    let dms: DirectMessages = serde_json::from_slice::<UninitDirectMessages>(&std::fs::read(path)?)?.try_into()?;
+    let exports = paths.iter().map(|path| UninitDirectMessages::read(path).with_context(|| format!("Failed to parse '{}'", path.display()))).collect::<Result<Vec<_>>>()?;
+    let mut channels = if args.compare.is_some() {
+        let mut groups = Vec::<Vec<UninitDirectMessages>>::new();
+        for export in exports {
+            match groups.iter_mut().find(|group| group[0].channel_id() == export.channel_id()) {
+                Some(group) => group.push(export),
+                None => groups.push(vec![export]),
+            }
+        }
+        groups.into_iter().map(|group| UninitDirectMessages::merge(group)?.try_into()).collect::<Result<Vec<DirectMessages>>>()?
+    } else {
+        vec![UninitDirectMessages::merge(exports)?.try_into()?]
+    };
+    let config = args.config.as_deref().map(Config::read).transpose()?.unwrap_or_default();
+    for dms in &mut channels {
+        dms.authors.relabel(args.author_label, &args.alias);
+        dms.merge_people(&config.people);
+        dms.timezone = args.timezone;
+        dms.day_start = args.day_start;
+        if matches!(args.timezone, TimezoneSetting::PerAuthor) {
+            for (_, author) in dms.authors.iter().filter(|(_, author)| author.timezone.is_none()) {
+                println!("Warning: no time zone configured for '{label}' ({id}), using local time", label = author.label, id = author.id);
+            }
+        }
+    }
+    let author_filter = AuthorFilter { include: args.include_author.clone(), exclude: args.exclude_author.clone() };
+    for pattern in author_filter.unmatched(&channels.iter().flat_map(|dms| dms.authors.iter().map(|(_, author)| author)).collect::<Vec<_>>()) {
+        println!("Warning: no author matches '{pattern}'");
+    }
+    for dms in &mut channels {
+        dms.filter_authors(&author_filter, args.group_totals);
+    }
     println!("Parsed DMs in {}", TimeQuantity::from(start.elapsed().as_millis() as usize));
 
-    enable_raw_mode()?;
-    let selections = select_data_calculations()?;
-    disable_raw_mode()?;
+    let reports = match &args.compare {
+        Some(ids) => run_comparisons(args, ids, &mut channels)?,
+        None => run_analyses(args, &mut channels[0])?,
+    };
+
+    let document = Document {
+        channels: channels.iter().map(|dms| DocumentChannel { id: dms.channel.id, name: dms.channel.name.clone() }).collect(),
+        notes: channels.iter().flat_map(DirectMessages::timezone_notes).unique().collect(),
+        reports,
+    };
+    let output = args.output.clone().unwrap_or_else(|| args.format.default_output());
+    let style = args.chart_style();
+    let rendered = args.format.render(&document, &output, &style)?;
+
+    if let Some(buf) = rendered.filter(|_| args.is_interactive()) {
+        println!("{buf}");
+        clipboard_rs::ClipboardContext::new().ok().context("Could not create clipboard")?.set_text(buf).ok().context("Failed to set clipboard content")?;
+        println!("Copied to clipboard!");
+    }
+    println!("Written to '{}'!", output.display());
+
+    if args.is_interactive() {
+        println!("Press enter to exit...");
+        std::io::stdin().read_line(&mut String::new())?;
+    }
+
+    Ok(())
+}
+
+fn run_analyses(args: &Args, dms: &mut DirectMessages) -> Result<Vec<Report>> {
+    let selections = if args.all {
+        ANALYSES.to_vec()
+    } else if !args.analysis.is_empty() {
+        args.analysis.iter().filter_map(|id| analysis::find(id)).collect::<Vec<_>>()
+    } else {
+        enable_raw_mode()?;
+        let selections = select_data_calculations();
+        disable_raw_mode()?;
+        selections?
+    };
+
+    let channels = std::slice::from_mut(dms);
+    let hull = restrict_in_place(args, &selections.iter().map(|selection| selection.id()).collect::<Vec<_>>(), channels)?;
+    let mut reports = Vec::with_capacity(selections.len());
+
+    for selection in selections {
+        let options = args.options_for(selection.id(), selection.options())?;
+        reports.push(run_windowed(args, selection.id(), channels, hull, |channels| selection.run(&channels[0], &options))?);
+    }
+
+    Ok(reports)
+}
+
+fn run_comparisons(args: &Args, ids: &[String], channels: &mut [DirectMessages]) -> Result<Vec<Report>> {
+    if channels.len() < 2 {
+        println!("Warning: only one channel was found, the comparison will have a single series");
+    }
 
-    let mut buf = String::new();
+    let selections = if ids.is_empty() {
+        COMPARISONS.to_vec()
+    } else {
+        ids.iter().filter_map(|id| analysis::comparison::find(id)).collect::<Vec<_>>()
+    };
 
+    let hull = restrict_in_place(args, &selections.iter().map(|selection| selection.id()).collect::<Vec<_>>(), channels)?;
+    let mut reports = Vec::with_capacity(selections.len());
     for selection in selections {
-        write!(&mut buf, "{}", selection(&dms)?)?;
+        let options = args.options_for(selection.id(), selection.options())?;
+        reports.push(run_windowed(args, selection.id(), channels, hull, |channels| selection.run(channels, &options))?);
     }
 
-    println!("{buf}");
-    clipboard_rs::ClipboardContext::new().ok().context("Could not create clipboard")?.set_text(buf.clone()).ok().context("Failed to set clipboard content")?;
-    println!("Copied to clipboard!");
-    std::fs::write("discord_dm_analysis.txt", buf)?;
-    println!("Written to 'discord_dm_analysis.txt'!");
+    Ok(reports)
+}
 
-    loop {}
+// drops the messages outside of every window the given analyses use, returning the range that is left
+fn restrict_in_place(args: &Args, ids: &[&str], channels: &mut [DirectMessages]) -> Result<DateRange> {
+    let hull = DateRange::hull(&ids.iter().map(|id| args.window_for(id)).collect::<Result<Vec<_>>>()?);
+    if !hull.is_unbounded() {
+        for dms in channels.iter_mut() {
+            dms.retain_within(hull);
+        }
+    }
+    Ok(hull)
 }
 
-fn select_data_calculations() -> Result<Vec<fn(&DirectMessages) -> Result<String>>> {
+// only analyses with a window narrower than what is left get their own copy of the messages
+fn run_windowed(args: &Args, id: &str, channels: &[DirectMessages], hull: DateRange, run: impl FnOnce(&[DirectMessages]) -> Result<Report>) -> Result<Report> {
+    let window = args.window_for(id)?;
+    let restricted = (window != hull).then(|| channels.iter().cloned().map(|mut dms| {
+        dms.retain_within(window);
+        dms
+    }).collect::<Vec<_>>());
+    let channels = restricted.as_deref().unwrap_or(channels);
+
+    let mut report = run(channels).with_context(|| format!("Failed to run '{id}'"))?;
+    if !window.is_unbounded() {
+        report.sections.insert(0, Section::Text(window.to_string()));
+    }
+    Ok(report)
+}
+
+fn collect_export_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut exports = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).filter(|path| path.as_ref().map_or(true, |path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")))).collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            exports.extend(entries);
+        } else {
+            exports.push(path.clone());
+        }
+    }
+    anyhow::ensure!(!exports.is_empty(), "No exports found");
+    Ok(exports)
+}
+
+fn select_data_calculations() -> Result<Vec<&'static dyn Analysis>> {
     enum SelectionInput {
         Finish,
         Toggle,
@@ -116,33 +405,12 @@ fn select_data_calculations() -> Result<Vec<fn(&DirectMessages) -> Result<String
         Ok(())
     }
 
-    const SELECTIONS: &[(&'static str, fn(&DirectMessages) -> Result<String>)] = &[
-        ("First Message", first_message),
-        ("Texting Frequency (Lifetime Graph; Weekly Buckets)", texting_frequency),
-        ("Top Call Lengths", top_call_lengths),
-        ("Total Call Lengths", total_call_lengths),
-        ("Longest Time Between Messages", longest_time_between_messages),
-        ("Longest Time Between Messages from Different Users", longest_time_between_different_users),
-        ("100 Most Said Words", most_said_words),
-        ("Words and Characters Written", words_and_characters_written),
-        ("Most Characters Said in a Day", most_characters_said_in_a_day),
-        ("Call Start Frequency (Time of Day Graph)", call_start_time_of_day_graph),
-        ("Text Frequency (Time of Day Graph)", text_time_of_day_graph),
-        ("Call Duration Graph (Annual Graph; Monthly Buckets)", call_duration_by_month_graph),
-        ("Call Duration Graph (Weekly Graph; Daily Buckets)", call_duration_by_day_of_week_graph),
-        ("Call Duration Graph (Daily Graph)", call_graph),
-        ("Call Duration Graph PNG Export (Daily Graph)", call_png),
-        ("Capitalization Rates (Annual Buckets)", capitalization_rates),
-        ("Edited Rates (Annual Buckets)", edit_rates),
-    ];
-
-
     execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
 
-    let mut selected = [false; const { SELECTIONS.len() }];
+    let mut selected = [false; const { ANALYSES.len() }];
     let mut selected_line = 0_usize;
 
-    for (idx, name, selected) in (0..SELECTIONS.len()).map(|idx| (idx, SELECTIONS[idx].0, selected[idx])) {
+    for (idx, name, selected) in (0..ANALYSES.len()).map(|idx| (idx, ANALYSES[idx].description(), selected[idx])) {
         display_line(name, selected, selected_line == idx)?;
     }
 
@@ -150,30 +418,32 @@ fn select_data_calculations() -> Result<Vec<fn(&DirectMessages) -> Result<String
         execute!(stdout(), MoveTo(0, selected_line as u16))?;
         match read_valid_input() {
             SelectionInput::Finish => {
-                execute!(stdout(), MoveTo(0, SELECTIONS.len() as u16))?;
-                return Ok((0..SELECTIONS.len()).filter(|&idx| selected[idx]).map(|idx| SELECTIONS[idx].1).collect::<Vec<_>>())
+                execute!(stdout(), MoveTo(0, ANALYSES.len() as u16))?;
+                return Ok((0..ANALYSES.len()).filter(|&idx| selected[idx]).map(|idx| ANALYSES[idx]).collect::<Vec<_>>())
             },
             SelectionInput::Toggle => {
                 selected[selected_line] = !selected[selected_line];
-                display_line(SELECTIONS[selected_line].0, selected[selected_line], true)?;
+                display_line(ANALYSES[selected_line].description(), selected[selected_line], true)?;
             },
             SelectionInput::Up => {
-                display_line(SELECTIONS[selected_line].0, selected[selected_line], false)?;
-                selected_line = (selected_line + SELECTIONS.len() - 1) % SELECTIONS.len();
+                display_line(ANALYSES[selected_line].description(), selected[selected_line], false)?;
+                selected_line = (selected_line + ANALYSES.len() - 1) % ANALYSES.len();
                 execute!(stdout(), MoveTo(0, selected_line as u16))?;
-                display_line(SELECTIONS[selected_line].0, selected[selected_line], true)?;
+                display_line(ANALYSES[selected_line].description(), selected[selected_line], true)?;
             },
             SelectionInput::Down => {
-                display_line(SELECTIONS[selected_line].0, selected[selected_line], false)?;
-                selected_line = (selected_line + 1) % SELECTIONS.len();
+                display_line(ANALYSES[selected_line].description(), selected[selected_line], false)?;
+                selected_line = (selected_line + 1) % ANALYSES.len();
                 execute!(stdout(), MoveTo(0, selected_line as u16))?;
-                display_line(SELECTIONS[selected_line].0, selected[selected_line], true)?;
+                display_line(ANALYSES[selected_line].description(), selected[selected_line], true)?;
             },
         }
     }
 }
 
 pub fn generate_progress_bar<T, S: Fn(&T) -> usize>(width: usize, full_char: char, empty_char: char, max: usize, quantities: &[T], sum: S) -> String {
+    // a graph with nothing in it draws empty bars
+    let max = max.max(1);
     let mut current_quantity = 0;
     let mut buf = String::with_capacity(width + 2);
     let _ = buf.write_char('[');
@@ -213,461 +483,33 @@ pub fn standard_deviation(sum: usize, iter: impl IntoIterator<Item=usize>, len:
     (len as f64).pow(-1.5) * f64::sqrt(accumulated as f64)
 }
 
-fn first_message(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# First Message")?;
-    let mut first_messages = vec![None; dms.channel.authors.len()];
-
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let author_idx = dms.channel.authors.iter().position(|author| *author == text.author.name).unwrap();
-        if first_messages[author_idx].is_none() {
-            first_messages[author_idx] = Some(&text.content);
-        }
-    }
-
-    for (author_idx, first_message) in first_messages.into_iter().enumerate() {
-        let author = dms.channel.authors[author_idx];
-        if let Some(first_message) = first_message {
-            writeln!(&mut buf, "{author} = {first_message}")?;
-        } else {
-            writeln!(&mut buf, "{author} has no messages")?;
-        }
-    }
-
-    Ok(buf)
-}
-
-fn texting_frequency(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n#Texting Frequency (Lifetime Graph; Weekly Buckets)")?;
-
-    let earliest_message_timestamp = dms.messages.iter().filter_map(Message::as_text_message).map(|text| text.timestamp).min().context("Expected a message")?;
-    let earliest_message_date = NaiveDate::from_yo_opt(earliest_message_timestamp.year(), earliest_message_timestamp.ordinal0() / 7 * 7 + 1).unwrap();
-
-    let mut graph = Graph::new(dms.channel.authors.clone(), 0, |idx| earliest_message_date.checked_add_days(Days::new(idx as u64 * 7)).unwrap().format("Week of %b %d, %Y").to_string(), dataset_sum, 50);
-
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let date = text.timestamp.date();
-        let delta = date - earliest_message_date;
-        let idx = delta.num_days() as usize / 7;
-        graph.add(text.author.name.as_str(), idx, 1);
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
+// nearest-rank percentile of an already sorted slice
+pub fn percentile<T: Copy>(sorted: &[T], pct: usize) -> Option<T> {
+    sorted.get((sorted.len() * pct).div_ceil(100).max(1) - 1).copied()
 }
 
-fn top_call_lengths(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Top 25 Call Lengths")?;
-    let mut lengths = dms.messages
-        .iter()
-        .filter_map(Message::as_call)
-        .map(Call::duration)
-        .collect::<Vec<_>>();
-
-    lengths.sort();
-
-    writeln!(&mut buf, "total calls: {}", lengths.len())?;
-    writeln!(&mut buf, "8 hour calls: {}", lengths.iter().filter(|&&delta| delta >= TimeDelta::hours(8)).count())?;
-
-    for (idx, duration) in lengths.into_iter().rev().take(25).enumerate() {
-        let len = TimeQuantity::from(duration);
-        writeln!(&mut buf, "{n}: length = {len:?}", n = idx + 1)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_nearest_rank() {
+        let sorted = (1..=10).collect::<Vec<_>>();
+        assert_eq!(percentile(&sorted, 0), Some(1));
+        assert_eq!(percentile(&sorted, 10), Some(1));
+        assert_eq!(percentile(&sorted, 11), Some(2));
+        assert_eq!(percentile(&sorted, 50), Some(5));
+        assert_eq!(percentile(&sorted, 90), Some(9));
+        assert_eq!(percentile(&sorted, 99), Some(10));
+        assert_eq!(percentile(&sorted, 100), Some(10));
+    }
+
+    #[test]
+    fn percentile_small_slices() {
+        assert_eq!(percentile::<usize>(&[], 50), None);
+        assert_eq!(percentile(&[7], 1), Some(7));
+        assert_eq!(percentile(&[7], 99), Some(7));
+        assert_eq!(percentile(&[1, 2], 50), Some(1));
+        assert_eq!(percentile(&[1, 2], 51), Some(2));
     }
-
-    Ok(buf)
-}
-
-fn total_call_lengths(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Total Call Lengths")?;
-    let len = TimeQuantity::from(dms.messages
-        .iter()
-        .filter_map(Message::as_call)
-        .map(Call::duration)
-        .sum::<TimeDelta>());
-
-    writeln!(&mut buf, "total length = {len}")?;
-
-    Ok(buf)
-}
-
-fn longest_time_between_messages(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Longest Time Between Messages")?;
-    let mut differences = Vec::new();
-
-    for (a, b) in dms.messages.iter().filter_map(Message::as_text_message).tuple_windows() {
-        differences.push((b.timestamp - a.timestamp, a.content.as_str(), a.author.name.as_str(), a.id, b.id, a.timestamp, b.timestamp));
-    }
-
-    differences.sort_by_key(|(a, _, _, _, _, _, _)| *a);
-
-    for (idx, (diff, content, author, first_id, second_id, first_timestamp, second_timestamp)) in differences.into_iter().rev().take(25).enumerate() {
-        let difference = TimeQuantity::from(diff.num_milliseconds() as usize);
-        writeln!(&mut buf, "{n}: diff = {difference}, first_timestamp = {first_timestamp}, second_timestamp = {second_timestamp}, first_id = {first_id}, second_id = {second_id} | content = {content:?}, author = {author}", n = idx + 1)?;
-    }
-
-    Ok(buf)
-}
-
-fn longest_time_between_different_users(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Longest Time (and most messages) Between Different Users")?;
-    let mut differences = Vec::new();
-
-    let mut prev_text = dms.messages.iter().filter_map(Message::as_text_message).next().context("Expected a text message")?;
-    let mut messages_between = 1_usize;
-
-    for text in dms.messages.iter().filter_map(Message::as_text_message).skip(1) {
-        if text.author != prev_text.author {
-            differences.push((text.timestamp - prev_text.timestamp, prev_text.content.as_str(), text.content.as_str(), prev_text.id, text.id, prev_text.timestamp, text.timestamp, messages_between));
-            prev_text = text;
-            messages_between = 1;
-        } else {
-            messages_between += 1;
-        }
-    }
-    
-    differences.sort_by_key(|(a, _, _, _, _, _, _, _)| *a);
-    for (idx, (diff, first_content, second_content, first_id, second_id, first_timestamp, second_timestamp, messages_between)) in differences.iter().rev().take(25).enumerate() {
-        let difference = TimeQuantity::from(diff.num_milliseconds() as usize);
-        writeln!(&mut buf, "{n}: diff = {difference}, messages_between = {messages_between}, first_timestamp = {first_timestamp}, second_timestamp = {second_timestamp}, first_id = {first_id}, second_id = {second_id} | first_content = {first_content:?} | second_content = {second_content:?}", n = idx + 1)?;
-    }
-    
-    writeln!(&mut buf)?;
-
-    differences.sort_by_key(|(_, _, _, _, _, _, _, a)| *a);
-    for (idx, (diff, first_content, second_content, first_id, second_id, first_timestamp, second_timestamp, messages_between)) in differences.iter().rev().take(25).enumerate() {
-        let difference = TimeQuantity::from(diff.num_milliseconds() as usize);
-        writeln!(&mut buf, "{n}: messages_between = {messages_between}, diff = {difference}, first_timestamp = {first_timestamp}, second_timestamp = {second_timestamp}, first_id = {first_id}, second_id = {second_id} | first_content = {first_content:?} | second_content = {second_content:?}", n = idx + 1)?;
-    }
-
-    Ok(buf)
-}
-
-fn most_said_words(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# 100 Most Said Words")?;
-    let mut map = FxHashMap::<String, usize>::default();
-
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let content = text.content_alphanumeric_lowercase();
-        for word in content.split_ascii_whitespace() {
-            *map.entry(word.to_owned()).or_insert(0) += 1;
-        }
-    }
-
-    let mut map = map.into_iter().collect::<Vec<_>>();
-    map.sort_by_key(|(_, b)| usize::MAX - *b);
-    for (idx, (word, count)) in map.into_iter().take(100).enumerate() {
-        writeln!(&mut buf, "{n}: {word} ({count})", n = idx + 1, count = count.to_formatted_string(&Locale::en))?;
-    }
-
-    Ok(buf)
-}
-
-fn words_and_characters_written(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Words and Characters Written (per person)")?;
-
-    let mut map = FxHashMap::<&str, (usize, usize)>::default();
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let written = text.content_alphanumeric_lowercase();
-        let words = written.split_ascii_whitespace().count();
-        let entry = map.entry(text.author.name.as_str()).or_insert((0, 0));
-        // if entry.0 < milestone && entry.0 + words >= milestone && let Some(word) = written.split_ascii_whitespace().skip(entry.0 + words - milestone).next() {
-        //     writeln!(&mut buf, "{author}'s {milestone_nth} word was '{word}' (https://discord.com/channels/@me/{author_id}/{msg_id}) @ {datetime}", author = text.author.name.as_str(), milestone_nth = nth(milestone), author_id = text.author.id, msg_id = text.id, datetime = text.timestamp);
-        // }
-        entry.0 += words;
-        entry.1 += text.content.len();
-    }
-
-    for (author, (words, characters)) in map.into_iter() {
-        writeln!(&mut buf, "{author} has written {words} words and {characters} characters", words = words.to_formatted_string(&Locale::en), characters = characters.to_formatted_string(&Locale::en))?;
-    }
-
-    Ok(buf)
-}
-
-fn most_characters_said_in_a_day(dms: &DirectMessages) -> Result<String> {
-    #[derive(Default)]
-    struct Measurement {
-        messages: usize,
-        words: usize,
-        characters: usize,
-        attachments: usize,
-    }
-
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Most Messages, Words, Characters, and Attachments Said In Day (sorted by messages)")?;
-
-    let mut map = FxHashMap::<NaiveDate, Measurement>::default();
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let chars = text.content.len();
-        let written = text.content.to_ascii_lowercase().chars().filter(|c| c.is_ascii_alphanumeric() || c.is_ascii_whitespace()).collect::<String>();
-        let date = text.timestamp.date();
-        let entry = map.entry(date).or_insert(Measurement::default());
-        entry.messages += 1;
-        entry.words += written.split_ascii_whitespace().count();
-        entry.characters += chars;
-        entry.attachments += text.attachments.len();
-    }
-
-    let mut map = map.into_iter().collect::<Vec<_>>();
-    map.sort_by_key(|(_, b)| usize::MAX - b.messages);
-    for (idx, (date, measurement)) in map.into_iter().take(25).enumerate() {
-        writeln!(&mut buf, "{n}: {date}: messages = {messages}, words = {words}, characters = {characters}, attachments = {attachments}", n = idx + 1, messages = measurement.messages.to_formatted_string(&Locale::en), words = measurement.words.to_formatted_string(&Locale::en), characters = measurement.characters.to_formatted_string(&Locale::en), attachments = measurement.attachments.to_formatted_string(&Locale::en))?;
-    }
-
-    Ok(buf)
-}
-
-fn call_start_time_of_day_graph(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Call Start Time of Day Graph (min = 15s, 15m groupings)")?;
-
-    let mut graph = Graph::new(dms.channel.authors.clone(), 5 * 4 + 2, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 4, minutes = (idx % 4) * 15), dataset_sum, 50);
-
-    for call in dms.messages.iter().filter_map(Message::as_call).filter(|call | call.duration() >= TimeDelta::seconds(15)) {
-        let datetime = call.start_timestamp;
-        let time = datetime.time();
-        let index = (time.hour() * 4 + time.minute() / 15) as usize;
-        graph.add(&call.author.name, index, 1);
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
-}
-
-fn text_time_of_day_graph(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Text Time of Day Graph (10m groupings)")?;
-
-    let mut graph = Graph::new(dms.channel.authors.clone(), 5 * 6 + 3, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 6, minutes = (idx % 6) * 10), dataset_sum, 50);
-
-    for text in dms.messages.iter().filter_map(Message::as_text_message) {
-        let datetime = text.timestamp;
-        let time = datetime.time();
-        let index = (time.hour() * 6 + time.minute() / 10) as usize;
-        graph.add(&text.author.name, index, 1);
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
-}
-
-fn call_duration_by_month_graph(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Call Duration by Month Graph (min = 15s)")?;
-
-    let mut graph = Graph::new(vec![dms.channel.name.as_str()], 0, |idx| format!("{month}", month = NaiveDate::from_ymd_opt(1, (idx + 1) as u32, 1).expect("Valid date").format("%h")), dataset_average, 50);
-
-    for call in dms.messages.iter().filter_map(Message::as_call).filter(|call | call.duration() >= TimeDelta::seconds(15)) {
-        let datetime = call.start_timestamp;
-        let date = datetime.date();
-        let index = date.month0() as usize;
-        graph.add(&dms.channel.name, index, TimeQuantity::from(call.duration()));
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
-}
-
-fn call_duration_by_day_of_week_graph(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Call Duration by Day of Week Graph (min = 15s)")?;
-
-    let mut graph = Graph::new(vec![dms.channel.name.as_str()], 0, |idx| Weekday::from_usize(idx).unwrap().to_string(), dataset_average, 50);
-
-    for call in dms.messages.iter().filter_map(Message::as_call).filter(|call | call.duration() >= TimeDelta::seconds(15)) {
-        let datetime = call.start_timestamp;
-        let index = datetime.date().weekday() as usize;
-        graph.add(&dms.channel.name, index, TimeQuantity::from(call.duration()));
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
-}
-
-fn call_graph(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Call Graph (10m groupings, min = 15s)")?;
-
-    let mut graph = Graph::new(dms.channel.authors.clone(), 5 * 6 + 3, |idx| format!("{hours:02}h{minutes:02}m", hours = idx / 6, minutes = (idx % 6) * 10), dataset_sum, 50);
-
-    for call in dms.messages.iter().filter_map(Message::as_call).filter(|call | call.duration() >= TimeDelta::seconds(15)) {
-        let start_time = call.start_timestamp;
-        let start_time_start = start_time.with_minute(start_time.minute() / 10 * 10).unwrap().with_second(0).unwrap().with_nanosecond(0).unwrap();
-        let mut index = (start_time.hour() * 6 + start_time.minute() / 10) as usize;
-        let end_time = call.end_timestamp;
-        let end_time_start = end_time.with_minute(end_time.minute() / 10 * 10).unwrap().with_second(0).unwrap().with_nanosecond(0).unwrap();
-        let head_duration = (start_time - start_time_start).num_milliseconds() as usize;
-        graph.add(&call.author.name, index, TimeQuantity::from(head_duration));
-        index += 1;
-        if start_time_start != end_time_start {
-            let mut remaining_millis = (call.duration().num_milliseconds() as usize).saturating_sub(head_duration);
-            while remaining_millis > 0 {
-                graph.add(&call.author.name, index % (24 * 6), TimeQuantity::from(remaining_millis.min(10 * 60 * 1000)));
-                remaining_millis = remaining_millis.saturating_sub(10 * 60 * 1000);
-                index += 1;
-            }
-        }
-    }
-
-    writeln!(&mut buf, "{graph}")?;
-
-    Ok(buf)
-}
-
-fn call_png(dms: &DirectMessages) -> Result<String> {
-    const RED_CHANNEL: [u8; 3] = [0x98, 0xE5, 0x5E];
-    const GREEN_CHANNEL: [u8; 3] = [0xC3, 0xC0, 0xAC];
-    const BLUE_CHANNEL: [u8; 3] = [0x79, 0x7B, 0xEC];
-
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Generating Call Graph Image (1m groupings)...")?;
-
-    writeln!(&mut buf, "Collecting Raw Data...")?;
-
-    const NUM_QUANTITIES: usize = 24 * 60 * 4;
-    const QUANTITY_PER: usize = 1000 * 60 * 60 * 24 / NUM_QUANTITIES;
-    let mut quantities: [Vec<usize>; NUM_QUANTITIES] = std::array::from_fn(|_| vec![0_usize; dms.channel.authors.len()]);
-
-    for call in dms.messages.iter().filter_map(Message::as_call).filter(|call | call.duration() >= TimeDelta::seconds(15)) {
-        let author_idx = dms.channel.authors.iter().position(|author| *author == call.author.name).unwrap();
-        let start_time = call.start_timestamp;
-        let start_time_start = start_time.with_second(0).unwrap().with_nanosecond(0).unwrap();
-        let mut index = (((start_time.hour() * 60 + start_time.minute()) * 60 + start_time.second()) * 1000) as usize / QUANTITY_PER;
-        let end_time = call.end_timestamp;
-        let end_time_start = end_time.with_second(0).unwrap().with_nanosecond(0).unwrap();
-        let head_duration = (start_time - start_time_start).num_milliseconds() as usize;
-        quantities[index][author_idx] += head_duration;
-        index = (index + 1) % NUM_QUANTITIES;
-        if start_time_start != end_time_start {
-            let mut remaining_millis = (call.duration().num_milliseconds() as usize).saturating_sub(head_duration);
-            while remaining_millis > 0 {
-                quantities[index][author_idx] += remaining_millis.min(QUANTITY_PER);
-                remaining_millis = remaining_millis.saturating_sub(QUANTITY_PER);
-                index = (index + 1) % NUM_QUANTITIES;
-            }
-        }
-    }
-
-    let (width, height) = (quantities.len(), (quantities.len() as f64 / (std::f64::consts::TAU)).ceil() as usize);
-    let max_ms = quantities.iter().map(|x| x.iter().copied().sum::<usize>() + 1).max().unwrap_or(0);
-    let ms_per_px = max_ms.div_ceil(height);
-    writeln!(&mut buf, "Generating Base Image...")?;
-    let mut image = image::RgbaImage::from_pixel(width as u32, height as u32, Rgba([0x31, 0x33, 0x38, 0xFF]));
-    for x in 0..width {
-        print!("Generating bars ({x} / {width}) ({pct:.1}%)...\r", pct = 100.0 * x as f64 / width as f64);
-        std::io::Write::flush(&mut stdout())?;
-        let quantities_index = (x + 11 * NUM_QUANTITIES / 48) % width;
-        let section = &*quantities[quantities_index];
-        let heights = (0..section.len()).map(|idx| height - 1 - section.iter().copied().take(idx).map(|x| x / ms_per_px).sum::<usize>()).collect::<Vec<_>>();
-        for (idx, (mut remaining_quantity, mut y)) in section.iter().copied().zip(heights.into_iter()).enumerate().rev() {
-            while remaining_quantity > 0 {
-                image.get_pixel_mut(x as u32, y as u32).blend(&Rgba([RED_CHANNEL[idx % RED_CHANNEL.len()], GREEN_CHANNEL[idx % GREEN_CHANNEL.len()], BLUE_CHANNEL[idx % BLUE_CHANNEL.len()], (remaining_quantity.min(ms_per_px) * 0xFF / ms_per_px) as u8]));
-                remaining_quantity = remaining_quantity.saturating_sub(ms_per_px);
-                y = y.saturating_sub(1);
-            }
-        }
-    }
-
-    writeln!(&mut buf, "Generating bars ({width} / {width}) (100.0%)...")?;
-    writeln!(&mut buf, "Writing file...")?;
-
-    let mut file = File::create(format!("Call Graph - {channel_name} - {id}.png", channel_name = dms.channel.name, id = dms.channel.id))?;
-    image.write_to(&mut file, ImageFormat::Png)?;
-
-    writeln!(&mut buf, "# Generated Call Graph Image")?;
-
-    Ok(buf)
-}
-
-fn capitalization_rates(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Capitalization Rates")?;
-
-    let first_year = dms.messages.iter().filter_map(Message::as_text_message).map(|text| text.timestamp).min().context("Expected at least one message sent")?.year();
-    let last_year = dms.messages.iter().filter_map(Message::as_text_message).map(|text| text.timestamp).max().context("Expected at least one message sent")?.year();
-
-    for year in first_year..=last_year {
-        let mut quantities = vec![(0_usize, 0_usize); dms.channel.authors.len()];
-
-        for text in dms.messages.iter().filter_map(Message::as_text_message).filter(|text| text.timestamp.year() == year && text.content.as_str().chars().next().is_some_and(char::is_alphabetic)) {
-            let author_idx = dms.channel.authors.iter().position(|author| *author == text.author.name).unwrap();
-            let (capitalized, uncapitalized) = &mut quantities[author_idx];
-            if text.content.as_str().chars().next().is_some_and(char::is_uppercase) {
-                *capitalized += 1;
-            } else {
-                *uncapitalized += 1;
-            }
-        }
-
-        writeln!(&mut buf, "\n## {year}")?;
-
-        for (author_idx, (capitalized, uncapitalized)) in quantities.into_iter().enumerate() {
-            let total = capitalized + uncapitalized;
-            let author_name = dms.channel.authors[author_idx];
-            writeln!(&mut buf, "{author_name}: {capitalized} / {total} ({pct:.2}%)", pct = 100.0 * capitalized as f64 / total as f64)?;
-        }
-    }
-
-    Ok(buf)
-}
-
-fn edit_rates(dms: &DirectMessages) -> Result<String> {
-    let mut buf = String::new();
-
-    writeln!(&mut buf, "\n# Edit Rates")?;
-
-    let first_year = dms.messages.iter().filter_map(Message::as_text_message).map(|text| text.timestamp).min().context("Expected at least one message sent")?.year();
-    let last_year = dms.messages.iter().filter_map(Message::as_text_message).map(|text| text.timestamp).max().context("Expected at least one message sent")?.year();
-
-    for year in first_year..=last_year {
-        let mut quantities = vec![(0_usize, 0_usize); dms.channel.authors.len()];
-
-        for text in dms.messages.iter().filter_map(Message::as_text_message).filter(|text| text.timestamp.year() == year) {
-            let author_idx = dms.channel.authors.iter().position(|author| *author == text.author.name).unwrap();
-            let (edited, unedited) = &mut quantities[author_idx];
-            if text.edited_timestamp.is_some() {
-                *edited += 1;
-            } else {
-                *unedited += 1;
-            }
-        }
-
-        writeln!(&mut buf, "\n## {year}")?;
-
-        for (author_idx, (edited, unedited)) in quantities.into_iter().enumerate() {
-            let total = edited + unedited;
-            let author_name = dms.channel.authors[author_idx];
-            writeln!(&mut buf, "{author_name}: {edited} / {total} ({pct:.2}%)", pct = 100.0 * edited as f64 / total as f64)?;
-        }
-    }
-
-    Ok(buf)
 }
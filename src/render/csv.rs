@@ -0,0 +1,79 @@
+use crate::data::{CalendarData, GraphData, Unit};
+use crate::render::{unique_path, Document};
+use crate::report::{Cell, Section, Table};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+pub fn render(document: &Document, output: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(output)?;
+
+    let mut written = Vec::new();
+    for report in &document.reports {
+        for section in &report.sections {
+            let (title, rows) = match section {
+                Section::Table(table) => (&table.title, table_rows(table)),
+                Section::Graph(graph) => (&graph.title, graph_rows(graph)),
+                Section::Calendar(calendar) => (&calendar.title, calendar_rows(calendar)),
+                _ => continue,
+            };
+            let path = unique_path(output, &format!("{id} - {title}", id = report.id), "csv", &written);
+            let mut writer = csv::Writer::from_path(&path)?;
+            for row in rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+fn table_rows(table: &Table) -> Vec<Vec<String>> {
+    let mut header = table.columns.iter().enumerate().map(|(idx, column)| {
+        if table.rows.iter().filter_map(|row| row.get(idx)).find(|cell| !matches!(cell, Cell::Empty)).is_some_and(|cell| matches!(cell, Cell::Duration(_))) {
+            format!("{column} (ms)")
+        } else {
+            column.to_string()
+        }
+    }).collect::<Vec<_>>();
+    if table.ranked {
+        header.insert(0, "rank".to_owned());
+    }
+
+    let mut rows = vec![header];
+    for (idx, row) in table.rows.iter().enumerate() {
+        let mut record = row.iter().map(Cell::raw).collect::<Vec<_>>();
+        if table.ranked {
+            record.insert(0, (idx + 1).to_string());
+        }
+        rows.push(record);
+    }
+    rows
+}
+
+fn unit_suffix(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Count => "",
+        Unit::Milliseconds => " (ms)",
+        Unit::BasisPoints => " (basis points)",
+    }
+}
+
+fn graph_rows(graph: &GraphData) -> Vec<Vec<String>> {
+    let suffix = unit_suffix(graph.unit);
+
+    let mut rows = vec![std::iter::once("bucket".to_owned()).chain(graph.series.iter().map(|series| format!("{series}{suffix}"))).collect::<Vec<_>>()];
+    for (label, values) in graph.labels.iter().zip(&graph.values) {
+        rows.push(std::iter::once(label.clone()).chain(values.iter().map(usize::to_string)).collect());
+    }
+    rows
+}
+
+fn calendar_rows(calendar: &CalendarData) -> Vec<Vec<String>> {
+    let mut rows = vec![vec!["date".to_owned(), format!("value{suffix}", suffix = unit_suffix(calendar.unit))]];
+    for (date, value) in &calendar.days {
+        rows.push(vec![date.to_string(), value.to_string()]);
+    }
+    rows
+}
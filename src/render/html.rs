@@ -0,0 +1,118 @@
+use crate::render::{escape, svg, ChartStyle, Document};
+use crate::report::{Cell, Section, Table};
+use itertools::Itertools;
+use std::fmt::Write;
+
+const STYLE: &str = r#"
+body { background: #313338; color: #DBDEE1; font-family: sans-serif; margin: 0 auto; max-width: 1100px; padding: 1em 2em; }
+nav a { color: #00A8FC; display: block; }
+section { border-top: 1px solid #4E5058; margin-top: 2em; }
+table { border-collapse: collapse; margin: 1em 0; width: 100%; }
+th, td { border: 1px solid #4E5058; padding: 4px 8px; text-align: left; vertical-align: top; }
+th { background: #2B2D31; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+td.content { white-space: pre-wrap; word-break: break-word; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 4px 16px; }
+dt { font-weight: bold; }
+svg { width: 100%; height: auto; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(th => th.addEventListener("click", () => {
+    const body = th.closest("table").tBodies[0];
+    const order = th.dataset.order === "asc" ? "desc" : "asc";
+    th.closest("tr").querySelectorAll("th").forEach(other => delete other.dataset.order);
+    th.dataset.order = order;
+    const key = row => row.cells[th.cellIndex].dataset.value;
+    const rows = Array.from(body.rows).sort((a, b) => {
+        const [x, y] = [key(a), key(b)];
+        const comparison = x !== "" && y !== "" && !isNaN(x) && !isNaN(y) ? x - y : x.localeCompare(y);
+        return order === "asc" ? comparison : -comparison;
+    });
+    rows.forEach(row => body.appendChild(row));
+}));
+"#;
+
+pub fn render(document: &Document, style: &ChartStyle) -> String {
+    let mut buf = String::new();
+    let title = format!("DM Analysis - {name}", name = escape(&document.channels.iter().map(|channel| channel.name.as_str()).join(", ")));
+
+    let _ = write!(&mut buf, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n");
+    for note in &document.notes {
+        let _ = writeln!(&mut buf, "<p>{note}</p>", note = escape(note));
+    }
+    buf.push_str("<nav>\n");
+    for report in &document.reports {
+        let _ = writeln!(&mut buf, "<a href=\"#{id}\">{title}</a>", id = report.id, title = escape(&report.title));
+    }
+    buf.push_str("</nav>\n");
+
+    for report in &document.reports {
+        let _ = writeln!(&mut buf, "<section id=\"{id}\">\n<h2>{title}</h2>", id = report.id, title = escape(&report.title));
+        for section in &report.sections {
+            render_section(&mut buf, section, style);
+        }
+        buf.push_str("</section>\n");
+    }
+
+    let _ = write!(&mut buf, "<script>{SCRIPT}</script>\n</body>\n</html>\n");
+    buf
+}
+
+fn render_section(buf: &mut String, section: &Section, style: &ChartStyle) {
+    match section {
+        Section::Heading(heading) => {
+            let _ = writeln!(buf, "<h3>{heading}</h3>", heading = escape(heading));
+        },
+        Section::Text(text) => {
+            if !text.is_empty() {
+                let _ = writeln!(buf, "<p>{text}</p>", text = escape(text));
+            }
+        },
+        Section::Stats(stats) => {
+            buf.push_str("<dl>\n");
+            for (key, value) in stats {
+                let _ = writeln!(buf, "<dt>{key}</dt><dd>{value}</dd>", key = escape(key), value = escape(&value.to_string()));
+            }
+            buf.push_str("</dl>\n");
+        },
+        Section::Table(table) => render_table(buf, table),
+        Section::Graph(graph) => {
+            let _ = writeln!(buf, "<figure>\n{svg}\n</figure>", svg = svg::render(graph, style));
+        },
+        Section::Calendar(calendar) => {
+            let _ = writeln!(buf, "<figure>\n{svg}\n</figure>", svg = svg::render_calendar(calendar, style));
+        },
+        Section::File(path) => {
+            let _ = writeln!(buf, "<p>Written to <code>{path}</code></p>", path = escape(&path.display().to_string()));
+        },
+    }
+}
+
+fn render_table(buf: &mut String, table: &Table) {
+    buf.push_str("<table class=\"sortable\">\n<thead><tr>");
+    if table.ranked {
+        buf.push_str("<th>#</th>");
+    }
+    for column in &table.columns {
+        let _ = write!(buf, "<th>{column}</th>", column = escape(column));
+    }
+    buf.push_str("</tr></thead>\n<tbody>\n");
+    for (idx, row) in table.rows.iter().enumerate() {
+        buf.push_str("<tr>");
+        if table.ranked {
+            let _ = write!(buf, "<td data-value=\"{n}\">{n}</td>", n = idx + 1);
+        }
+        for cell in row {
+            let class = if matches!(cell, Cell::Content(_)) { " class=\"content\"" } else { "" };
+            let text = match cell {
+                Cell::Content(content) => content.clone(),
+                cell => cell.to_string(),
+            };
+            let _ = write!(buf, "<td{class} data-value=\"{value}\">{text}</td>", value = escape(&cell.raw()), text = escape(&text));
+        }
+        buf.push_str("</tr>\n");
+    }
+    buf.push_str("</tbody>\n</table>\n");
+}
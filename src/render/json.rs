@@ -0,0 +1,6 @@
+use crate::render::Document;
+use anyhow::Result;
+
+pub fn render(document: &Document) -> Result<String> {
+    Ok(serde_json::to_string_pretty(document)?)
+}
@@ -0,0 +1,143 @@
+pub mod csv;
+pub mod html;
+pub mod json;
+pub mod png;
+pub mod svg;
+pub mod text;
+
+use crate::data::{GraphData, SERIES_COLORS};
+use crate::report::{serialize_id, Report};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+pub struct Document {
+    pub channels: Vec<DocumentChannel>,
+    pub notes: Vec<String>,
+    pub reports: Vec<Report>,
+}
+
+#[derive(Serialize)]
+pub struct DocumentChannel {
+    #[serde(serialize_with = "serialize_id")]
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Clone)]
+pub struct ChartStyle {
+    pub width: u32,
+    pub height: u32,
+    pub colors: Vec<[u8; 3]>,
+    pub background: Option<[u8; 4]>,
+}
+
+impl ChartStyle {
+    pub fn series_color(&self, graph: &GraphData, idx: usize) -> [u8; 3] {
+        if let Some(color) = graph.colors.get(idx).copied().flatten() {
+            color
+        } else if self.colors.is_empty() {
+            SERIES_COLORS[idx % SERIES_COLORS.len()]
+        } else {
+            self.colors[idx % self.colors.len()]
+        }
+    }
+}
+
+impl Default for ChartStyle {
+    fn default() -> Self {
+        Self {
+            width: 960,
+            height: 400,
+            colors: Vec::new(),
+            background: None,
+        }
+    }
+}
+
+pub fn parse_color(s: &str) -> Result<Vec<u8>> {
+    let hex = s.trim_start_matches('#');
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        bail!("Expected a colour in the form RRGGBB or RRGGBBAA, found '{s}'");
+    }
+    (0..hex.len()).step_by(2).map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).with_context(|| format!("Invalid colour '{s}'"))).collect()
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Html,
+    Svg,
+    Png,
+}
+
+impl Format {
+    pub fn default_output(self) -> PathBuf {
+        PathBuf::from(match self {
+            Format::Text => "discord_dm_analysis.txt",
+            Format::Json => "discord_dm_analysis.json",
+            Format::Csv => "discord_dm_analysis",
+            Format::Html => "discord_dm_analysis.html",
+            Format::Svg => "discord_dm_analysis",
+            Format::Png => "discord_dm_analysis",
+        })
+    }
+
+    pub fn render(self, document: &Document, output: &Path, style: &ChartStyle) -> Result<Option<String>> {
+        let rendered = match self {
+            Format::Text => text::render(document),
+            Format::Json => json::render(document)?,
+            Format::Html => {
+                std::fs::write(output, html::render(document, style))?;
+                return Ok(None)
+            },
+            Format::Csv => {
+                let written = csv::render(document, output)?;
+                println!("Written {n} CSV files", n = written.len());
+                return Ok(None)
+            },
+            Format::Svg => {
+                let written = svg::render_files(document, output, style)?;
+                println!("Written {n} SVG files", n = written.len());
+                return Ok(None)
+            },
+            Format::Png => {
+                let written = png::render_files(document, output, style)?;
+                println!("Written {n} PNG files", n = written.len());
+                return Ok(None)
+            },
+        };
+        std::fs::write(output, &rendered)?;
+        Ok(Some(rendered))
+    }
+}
+
+pub fn unique_path(directory: &Path, name: &str, extension: &str, taken: &[PathBuf]) -> PathBuf {
+    let name = name.chars().map(|c| if c.is_alphanumeric() || " -_()".contains(c) { c } else { '_' }).collect::<String>();
+    let mut path = directory.join(format!("{name}.{extension}"));
+    let mut n = 2;
+    while taken.contains(&path) {
+        path = directory.join(format!("{name} ({n}).{extension}"));
+        n += 1;
+    }
+    path
+}
+
+pub fn escape(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&#39;"),
+            c => buf.push(c),
+        }
+    }
+    buf
+}
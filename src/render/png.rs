@@ -0,0 +1,158 @@
+use crate::data::{CalendarData, GraphData, CALENDAR_COLORS};
+use crate::render::{unique_path, ChartStyle, Document};
+use crate::report::Section;
+use anyhow::Result;
+use chrono::Datelike;
+use image::{ImageFormat, Pixel, Rgba, RgbaImage};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_BACKGROUND: [u8; 4] = [0x31, 0x33, 0x38, 0xFF];
+
+pub fn render_files(document: &Document, output: &Path, style: &ChartStyle) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(output)?;
+
+    let mut written = Vec::new();
+    for report in &document.reports {
+        for section in &report.sections {
+            let (title, image) = match section {
+                Section::Graph(graph) => (&graph.title, render(graph, style)),
+                Section::Calendar(calendar) => (&calendar.title, render_calendar(calendar, style)),
+                _ => continue,
+            };
+            let path = unique_path(output, &format!("{id} - {title}", id = report.id), "png", &written);
+            image.write_to(&mut File::create(&path)?, ImageFormat::Png)?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+pub fn write(graph: &GraphData, style: &ChartStyle, path: &Path) -> Result<()> {
+    let mut file = File::create(path)?;
+    render(graph, style).write_to(&mut file, ImageFormat::Png)?;
+    Ok(())
+}
+
+pub fn write_calendar(calendar: &CalendarData, style: &ChartStyle, path: &Path) -> Result<()> {
+    let mut file = File::create(path)?;
+    render_calendar(calendar, style).write_to(&mut file, ImageFormat::Png)?;
+    Ok(())
+}
+
+pub fn render(graph: &GraphData, style: &ChartStyle) -> RgbaImage {
+    let (width, height) = (style.width.max(1) as usize, style.height.max(1) as usize);
+    let mut image = RgbaImage::from_pixel(width as u32, height as u32, Rgba(style.background.unwrap_or(DEFAULT_BACKGROUND)));
+    if graph.values.is_empty() {
+        return image
+    }
+
+    let scale = height as f64 / graph.totals().max().unwrap_or(0).max(1) as f64;
+    for x in 0..width {
+        let values = &graph.values[x * graph.values.len() / width];
+        let mut bottom = 0.0;
+        for (idx, value) in values.iter().copied().enumerate() {
+            let top = bottom + value as f64 * scale;
+            let [r, g, b] = style.series_color(graph, idx);
+            // rows the bar only partly covers are blended by how much of them it covers
+            for row in bottom.floor() as usize..(top.ceil() as usize).min(height) {
+                let coverage = (top.min(row as f64 + 1.0) - bottom.max(row as f64)).clamp(0.0, 1.0);
+                image.get_pixel_mut(x as u32, (height - 1 - row) as u32).blend(&Rgba([r, g, b, (coverage * 255.0).round() as u8]));
+            }
+            bottom = top;
+        }
+    }
+
+    image
+}
+
+// one panel per year, stacked from the earliest year down, each headed by its year and month initials
+pub fn render_calendar(calendar: &CalendarData, style: &ChartStyle) -> RgbaImage {
+    const CELL: u32 = 12;
+    const STEP: u32 = CELL + 2;
+    const MARGIN: u32 = 12;
+    const PANEL_GAP: u32 = 2 * CELL;
+    const LABEL_HEIGHT: u32 = 5 * GLYPH_SCALE + 4;
+    const LEFT: u32 = MARGIN + 3 * GLYPH_SCALE + 4;
+    const PANEL_HEIGHT: u32 = 2 * LABEL_HEIGHT + 7 * STEP;
+    const LABEL_COLOR: Rgba<u8> = Rgba([0x99, 0x99, 0x99, 0xFF]);
+
+    let weeks = calendar.years().map(CalendarData::weeks).max().unwrap_or(0) as u32;
+    let years = calendar.years().count() as u32;
+    let width = LEFT + MARGIN + weeks * STEP;
+    let height = MARGIN * 2 + years * PANEL_HEIGHT + years.saturating_sub(1) * PANEL_GAP;
+    let mut image = RgbaImage::from_pixel(width.max(1), height.max(1), Rgba(style.background.unwrap_or(DEFAULT_BACKGROUND)));
+
+    let max = calendar.max();
+    for (idx, year) in calendar.years().enumerate() {
+        let top = MARGIN + idx as u32 * (PANEL_HEIGHT + PANEL_GAP);
+        if idx > 0 {
+            fill(&mut image, MARGIN, top - PANEL_GAP / 2, width - 2 * MARGIN, 1, LABEL_COLOR);
+        }
+        draw_text(&mut image, &year.to_string(), LEFT, top, LABEL_COLOR);
+        let grid = top + 2 * LABEL_HEIGHT;
+        for (weekday, label) in [(0, "M"), (2, "W"), (4, "F")] {
+            draw_text(&mut image, label, MARGIN, grid + weekday * STEP + (CELL - 5 * GLYPH_SCALE) / 2, LABEL_COLOR);
+        }
+        for (week, weekday, date) in CalendarData::cells(year) {
+            if date.day() == 1 {
+                draw_text(&mut image, &MONTH_INITIALS[date.month0() as usize..][..1], LEFT + week as u32 * STEP, top + LABEL_HEIGHT, LABEL_COLOR);
+            }
+            let [r, g, b] = CALENDAR_COLORS[calendar.level(date, max)];
+            fill(&mut image, LEFT + week as u32 * STEP, grid + weekday as u32 * STEP, CELL, CELL, Rgba([r, g, b, 0xFF]));
+        }
+    }
+
+    image
+}
+
+const GLYPH_SCALE: u32 = 2;
+const MONTH_INITIALS: &str = "JFMAMJJASOND";
+
+// 3x5 glyphs for the few characters calendar labels need, one row per byte
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b101, 0b111, 0b111, 0b111, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        _ => [0; 5],
+    }
+}
+
+fn draw_text(image: &mut RgbaImage, text: &str, x: u32, y: u32, color: Rgba<u8>) {
+    for (idx, c) in text.chars().enumerate() {
+        let left = x + idx as u32 * 4 * GLYPH_SCALE;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for col in (0..3).filter(|col| bits & (0b100 >> col) != 0) {
+                fill(image, left + col * GLYPH_SCALE, y + row as u32 * GLYPH_SCALE, GLYPH_SCALE, GLYPH_SCALE, color);
+            }
+        }
+    }
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for dy in 0..height {
+        for dx in 0..width {
+            if let Some(pixel) = image.get_pixel_mut_checked(x + dx, y + dy) {
+                *pixel = color;
+            }
+        }
+    }
+}
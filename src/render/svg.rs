@@ -0,0 +1,145 @@
+use crate::data::{CalendarData, GraphData, Unit, CALENDAR_COLORS};
+use crate::render::{escape, unique_path, ChartStyle, Document};
+use crate::report::Section;
+use anyhow::Result;
+use chrono::Datelike;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 160.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 90.0;
+const MAX_LABELS: usize = 48;
+const Y_TICKS: usize = 5;
+const CALENDAR_CELL: f64 = 11.0;
+const CALENDAR_GAP: f64 = 2.0;
+const CALENDAR_LEFT: f64 = 36.0;
+const CALENDAR_YEAR_HEIGHT: f64 = 40.0 + 7.0 * (CALENDAR_CELL + CALENDAR_GAP) + 16.0;
+
+pub fn render_files(document: &Document, output: &Path, style: &ChartStyle) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(output)?;
+
+    let mut written = Vec::new();
+    for report in &document.reports {
+        for section in &report.sections {
+            let (title, svg) = match section {
+                Section::Graph(graph) => (&graph.title, render(graph, style)),
+                Section::Calendar(calendar) => (&calendar.title, render_calendar(calendar, style)),
+                _ => continue,
+            };
+            let path = unique_path(output, &format!("{id} - {title}", id = report.id), "svg", &written);
+            std::fs::write(&path, format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{svg}\n"))?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+pub fn render(graph: &GraphData, style: &ChartStyle) -> String {
+    let mut buf = String::new();
+    let (width, height) = (style.width as f64, style.height as f64);
+    let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+    let plot_height = (height - MARGIN_TOP - MARGIN_BOTTOM).max(1.0);
+    let plot_bottom = MARGIN_TOP + plot_height;
+    let step = tick_step(graph.unit, graph.totals().max().unwrap_or(0), Y_TICKS);
+    let max = (graph.totals().max().unwrap_or(0).div_ceil(step) * step).max(step) as f64;
+    let bar_width = plot_width / graph.labels.len().max(1) as f64;
+    let label_step = graph.labels.len().div_ceil(MAX_LABELS).max(1);
+
+    let _ = write!(&mut buf, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="11">"#);
+    let _ = write!(&mut buf, r#"<title>{title}</title>"#, title = escape(&graph.title));
+    if let Some([r, g, b, a]) = style.background {
+        let _ = write!(&mut buf, r##"<rect width="100%" height="100%" fill="#{r:02X}{g:02X}{b:02X}" fill-opacity="{opacity:.3}"/>"##, opacity = a as f64 / 255.0);
+    }
+    let _ = write!(&mut buf, r#"<text x="{x}" y="{y}" text-anchor="middle" font-size="16" fill="currentColor">{title}</text>"#, x = MARGIN_LEFT + plot_width / 2.0, y = MARGIN_TOP / 2.0 + 6.0, title = escape(&graph.title));
+
+    let mut tick = 0;
+    while tick as f64 <= max {
+        let y = plot_bottom - tick as f64 / max * plot_height;
+        let _ = write!(&mut buf, r#"<line x1="{MARGIN_LEFT}" y1="{y:.2}" x2="{x2}" y2="{y:.2}" stroke="currentColor" stroke-opacity="0.2"/>"#, x2 = MARGIN_LEFT + plot_width);
+        let _ = write!(&mut buf, r#"<text x="{x}" y="{text_y:.2}" text-anchor="end" fill="currentColor">{label}</text>"#, x = MARGIN_LEFT - 6.0, text_y = y + 4.0, label = escape(&graph.format_value(tick)));
+        tick += step;
+    }
+
+    for (idx, (label, values)) in graph.labels.iter().zip(&graph.values).enumerate() {
+        let x = MARGIN_LEFT + idx as f64 * bar_width;
+        let mut y = plot_bottom;
+        for (series_idx, value) in values.iter().copied().enumerate().filter(|(_, value)| *value > 0) {
+            let height = value as f64 / max * plot_height;
+            y -= height;
+            let [r, g, b] = style.series_color(graph, series_idx);
+            let _ = write!(&mut buf, r##"<rect x="{x:.2}" y="{y:.2}" width="{width:.2}" height="{height:.2}" fill="#{r:02X}{g:02X}{b:02X}"><title>{label}: {series} = {value}</title></rect>"##, width = (bar_width * 0.9).max(0.5), label = escape(label), series = escape(&graph.series[series_idx]), value = escape(&graph.format_value(value)));
+        }
+        if idx % label_step == 0 {
+            let label_x = x + bar_width / 2.0;
+            let _ = write!(&mut buf, r#"<line x1="{label_x:.2}" y1="{plot_bottom:.2}" x2="{label_x:.2}" y2="{y2:.2}" stroke="currentColor"/>"#, y2 = plot_bottom + 4.0);
+            let _ = write!(&mut buf, r#"<text x="{label_x:.2}" y="{label_y:.2}" transform="rotate(60 {label_x:.2} {label_y:.2})" fill="currentColor">{label}</text>"#, label_y = plot_bottom + 10.0, label = escape(label));
+        }
+    }
+
+    let _ = write!(&mut buf, r#"<line x1="{MARGIN_LEFT}" y1="{plot_bottom:.2}" x2="{x2}" y2="{plot_bottom:.2}" stroke="currentColor"/>"#, x2 = MARGIN_LEFT + plot_width);
+    let _ = write!(&mut buf, r#"<line x1="{MARGIN_LEFT}" y1="{MARGIN_TOP}" x2="{MARGIN_LEFT}" y2="{plot_bottom:.2}" stroke="currentColor"/>"#);
+
+    for (idx, series) in graph.series.iter().enumerate() {
+        let [r, g, b] = style.series_color(graph, idx);
+        let x = width - MARGIN_RIGHT + 16.0;
+        let y = MARGIN_TOP + idx as f64 * 18.0;
+        let _ = write!(&mut buf, r##"<rect x="{x}" y="{y}" width="12" height="12" fill="#{r:02X}{g:02X}{b:02X}"/><text x="{text_x}" y="{text_y}" fill="currentColor">{series}</text>"##, text_x = x + 18.0, text_y = y + 10.0, series = escape(series));
+    }
+
+    buf.push_str("</svg>");
+    buf
+}
+
+pub fn render_calendar(calendar: &CalendarData, style: &ChartStyle) -> String {
+    const WEEKDAYS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+
+    let mut buf = String::new();
+    let step = CALENDAR_CELL + CALENDAR_GAP;
+    let weeks = calendar.years().map(CalendarData::weeks).max().unwrap_or(0);
+    let width = CALENDAR_LEFT + weeks as f64 * step + CALENDAR_GAP;
+    let height = calendar.years().count() as f64 * CALENDAR_YEAR_HEIGHT + 20.0;
+    let max = calendar.max();
+
+    let _ = write!(&mut buf, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="10">"#);
+    let _ = write!(&mut buf, r#"<title>{title}</title>"#, title = escape(&calendar.title));
+    if let Some([r, g, b, a]) = style.background {
+        let _ = write!(&mut buf, r##"<rect width="100%" height="100%" fill="#{r:02X}{g:02X}{b:02X}" fill-opacity="{opacity:.3}"/>"##, opacity = a as f64 / 255.0);
+    }
+    for (idx, year) in calendar.years().enumerate() {
+        let top = idx as f64 * CALENDAR_YEAR_HEIGHT + 20.0;
+        let _ = write!(&mut buf, r#"<text x="0" y="{top}" font-size="13" fill="currentColor">{year}</text>"#);
+        for (week, _, date) in CalendarData::cells(year).filter(|(_, _, date)| date.day() == 1) {
+            let _ = write!(&mut buf, r#"<text x="{x}" y="{y}" fill="currentColor">{month}</text>"#, x = CALENDAR_LEFT + week as f64 * step, y = top + 18.0, month = date.format("%b"));
+        }
+        for (weekday, label) in WEEKDAYS.iter().enumerate().filter(|(_, label)| !label.is_empty()) {
+            let _ = write!(&mut buf, r#"<text x="0" y="{y}" fill="currentColor">{label}</text>"#, y = top + 24.0 + weekday as f64 * step + CALENDAR_CELL - 2.0);
+        }
+        for (week, weekday, date) in CalendarData::cells(year) {
+            let [r, g, b] = CALENDAR_COLORS[calendar.level(date, max)];
+            let _ = write!(&mut buf, r##"<rect x="{x}" y="{y}" width="{CALENDAR_CELL}" height="{CALENDAR_CELL}" rx="2" fill="#{r:02X}{g:02X}{b:02X}"><title>{date}: {value}</title></rect>"##, x = CALENDAR_LEFT + week as f64 * step, y = top + 24.0 + weekday as f64 * step, value = escape(&calendar.format_value(calendar.value(date))));
+        }
+    }
+
+    buf.push_str("</svg>");
+    buf
+}
+
+fn tick_step(unit: Unit, max: usize, ticks: usize) -> usize {
+    const DAY: usize = 24 * 60 * 60 * 1000;
+    const DURATION_STEPS: &[usize] = &[1000, 5000, 15_000, 30_000, 60_000, 5 * 60_000, 10 * 60_000, 15 * 60_000, 30 * 60_000, 60 * 60_000, 2 * 60 * 60_000, 3 * 60 * 60_000, 6 * 60 * 60_000, 12 * 60 * 60_000, DAY];
+
+    let raw = max.div_ceil(ticks).max(1);
+    match unit {
+        Unit::Milliseconds if raw <= DAY => DURATION_STEPS.iter().copied().find(|step| *step >= raw).unwrap_or(DAY),
+        Unit::Milliseconds => nice_step(raw.div_ceil(DAY)) * DAY,
+        Unit::Count | Unit::BasisPoints => nice_step(raw),
+    }
+}
+
+fn nice_step(raw: usize) -> usize {
+    let magnitude = 10_usize.pow(raw.ilog10());
+    [1, 2, 5, 10].into_iter().map(|n| n * magnitude).find(|step| *step >= raw).unwrap_or(raw)
+}
@@ -0,0 +1,13 @@
+use crate::render::Document;
+use std::fmt::Write;
+
+pub fn render(document: &Document) -> String {
+    let mut buf = String::new();
+    for note in &document.notes {
+        let _ = writeln!(&mut buf, "{note}");
+    }
+    for report in &document.reports {
+        let _ = write!(&mut buf, "{report}");
+    }
+    buf
+}
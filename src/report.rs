@@ -0,0 +1,222 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use chrono::{NaiveDate, NaiveDateTime};
+use num_format::{Locale, ToFormattedString};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use crate::data::{CalendarData, GraphData, TimeQuantity};
+
+#[derive(Serialize)]
+pub struct Report {
+    pub id: &'static str,
+    pub title: String,
+    pub sections: Vec<Section>,
+}
+
+impl Report {
+    pub fn new(id: &'static str, title: impl Into<String>) -> Self {
+        Self {
+            id,
+            title: title.into(),
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, section: impl Into<Section>) {
+        self.sections.push(section.into());
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "\n# {title}", title = self.title)?;
+        for section in &self.sections {
+            write!(f, "{section}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Section {
+    Heading(String),
+    Text(String),
+    Stats(#[serde(serialize_with = "serialize_stats")] Vec<(String, Cell)>),
+    Table(Table),
+    Graph(GraphData),
+    Calendar(CalendarData),
+    File(PathBuf),
+}
+
+pub fn serialize_id<S: Serializer>(id: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(id)
+}
+
+fn serialize_stats<S: Serializer>(stats: &[(String, Cell)], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(stats.iter().map(|(key, value)| (key, value)))
+}
+
+impl From<Table> for Section {
+    fn from(table: Table) -> Self {
+        Section::Table(table)
+    }
+}
+
+impl From<GraphData> for Section {
+    fn from(graph: GraphData) -> Self {
+        Section::Graph(graph)
+    }
+}
+
+impl From<CalendarData> for Section {
+    fn from(calendar: CalendarData) -> Self {
+        Section::Calendar(calendar)
+    }
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Section::Heading(heading) => writeln!(f, "\n## {heading}"),
+            Section::Text(text) => writeln!(f, "{text}"),
+            Section::Stats(stats) => {
+                for (key, value) in stats {
+                    writeln!(f, "{key} = {value}")?;
+                }
+                Ok(())
+            },
+            Section::Table(table) => write!(f, "{table}"),
+            Section::Graph(graph) => writeln!(f, "{graph}"),
+            Section::Calendar(calendar) => writeln!(f, "{calendar}"),
+            Section::File(path) => writeln!(f, "Written to '{path}'", path = path.display()),
+        }
+    }
+}
+
+pub struct Table {
+    pub title: String,
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+    pub ranked: bool,
+}
+
+impl Table {
+    pub fn new(title: impl Into<String>, columns: Vec<&'static str>) -> Self {
+        Self {
+            title: title.into(),
+            columns,
+            rows: Vec::new(),
+            ranked: false,
+        }
+    }
+
+    pub fn ranked(title: impl Into<String>, columns: Vec<&'static str>) -> Self {
+        Self {
+            ranked: true,
+            ..Self::new(title, columns)
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+}
+
+impl Serialize for Table {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Row<'a>(&'a [&'static str], &'a [Cell]);
+
+        impl Serialize for Row<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().zip(self.1))
+            }
+        }
+
+        let mut table = serializer.serialize_struct("Table", 4)?;
+        table.serialize_field("title", &self.title)?;
+        table.serialize_field("columns", &self.columns)?;
+        table.serialize_field("ranked", &self.ranked)?;
+        table.serialize_field("rows", &self.rows.iter().map(|row| Row(&self.columns, row)).collect::<Vec<_>>())?;
+        table.end()
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, row) in self.rows.iter().enumerate() {
+            if self.ranked {
+                write!(f, "{n}: ", n = idx + 1)?;
+            }
+            for (column_idx, (column, cell)) in self.columns.iter().zip(row).enumerate() {
+                if column_idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{column} = {cell}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub enum Cell {
+    Text(String),
+    Content(String),
+    Integer(usize),
+    Float(f64),
+    Percentage(f64),
+    Duration(TimeQuantity),
+    Timestamp(NaiveDateTime),
+    Date(NaiveDate),
+    Id(u64),
+    Empty,
+}
+
+impl Cell {
+    pub fn raw(&self) -> String {
+        match self {
+            Cell::Text(text) | Cell::Content(text) => text.clone(),
+            Cell::Integer(n) => n.to_string(),
+            Cell::Float(n) | Cell::Percentage(n) => n.to_string(),
+            Cell::Duration(duration) => usize::from(*duration).to_string(),
+            Cell::Timestamp(timestamp) => timestamp.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            Cell::Date(date) => date.to_string(),
+            Cell::Id(id) => id.to_string(),
+            Cell::Empty => String::new(),
+        }
+    }
+}
+
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cell::Text(text) | Cell::Content(text) => serializer.serialize_str(text),
+            Cell::Integer(n) => serializer.serialize_u64(*n as u64),
+            Cell::Float(n) | Cell::Percentage(n) => serializer.serialize_f64(*n),
+            Cell::Duration(duration) => duration.serialize(serializer),
+            Cell::Timestamp(timestamp) => timestamp.serialize(serializer),
+            Cell::Date(date) => date.serialize(serializer),
+            Cell::Id(id) => serialize_id(id, serializer),
+            Cell::Empty => serializer.serialize_none(),
+        }
+    }
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => write!(f, "{text}"),
+            Cell::Content(content) => write!(f, "{content:?}"),
+            Cell::Integer(n) => write!(f, "{n}", n = n.to_formatted_string(&Locale::en)),
+            Cell::Float(n) => write!(f, "{n:.2}"),
+            Cell::Percentage(pct) => write!(f, "{pct:.2}%"),
+            Cell::Duration(duration) => write!(f, "{duration}"),
+            Cell::Timestamp(timestamp) => write!(f, "{timestamp}"),
+            Cell::Date(date) => write!(f, "{date}"),
+            Cell::Id(id) => write!(f, "{id}"),
+            Cell::Empty => write!(f, "-"),
+        }
+    }
+}
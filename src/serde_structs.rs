@@ -1,16 +1,25 @@
-use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeDelta};
+use crate::config::Person;
+use crate::filter::{AuthorFilter, DateRange};
+use crate::time::{TimezoneSetting, Zone, ZoneSchedule};
+use clap::ValueEnum;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
 use itertools::Itertools;
-use serde::de::Error;
+use serde::de::{Error, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
-use serde_json::Value;
 use serde_this_or_that::as_u64;
-use std::ops::Deref;
-use fxhash::{FxBuildHasher, FxHashMap};
-use parking_lot::RwLock;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::fmt::Formatter;
+use std::ops::{Deref, Index};
+use std::path::Path;
+use fxhash::FxHashMap;
+
+// stands in for the authors grouped together by `--group-totals`, snowflakes are never 0
+pub const GROUP_AUTHOR_ID: u64 = 0;
 
 pub fn opt_timestamp_from_spec<'de, D: Deserializer<'de>>(deserializer: D) -> anyhow::Result<Option<NaiveDateTime>, D::Error> {
     Ok(match String::deserialize(deserializer) {
-        Ok(x) => Some(x.parse::<DateTime<FixedOffset>>().map_err(|_| Error::custom("Could not parse timestamp"))?.with_timezone(&Local).naive_local()),
+        Ok(x) => Some(x.parse::<DateTime<FixedOffset>>().map_err(|_| Error::custom("Could not parse timestamp"))?.naive_utc()),
         Err(_) => None,
     })
 }
@@ -22,18 +31,115 @@ pub fn timestamp_from_spec<'de, D: Deserializer<'de>>(deserializer: D) -> anyhow
 #[derive(Deserialize)]
 pub struct UninitDirectMessages {
     channel: ChannelInfo,
+    #[serde(rename = "messages")]
+    log: MessageLog,
+}
+
+impl UninitDirectMessages {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+        let total = file.metadata()?.len();
+        let reader = ProgressReader { inner: file, path, read: 0, total, last_percent: None };
+        // streamed so that multi-gigabyte exports never have to fit in memory as a whole
+        let mut dms = Self::deserialize(&mut serde_json::Deserializer::from_reader(BufReader::with_capacity(1 << 20, reader)))?;
+        dms.log.messages.shrink_to_fit();
+        println!();
+        Ok(dms)
+    }
+
+    pub fn channel_id(&self) -> u64 {
+        self.channel.id
+    }
+
+    pub fn merge(exports: Vec<Self>) -> anyhow::Result<Self> {
+        let mut exports = exports.into_iter();
+        let Some(Self { channel, log: MessageLog { mut authors, mut messages } }) = exports.next() else { anyhow::bail!("Expected at least one export") };
+
+        for export in exports {
+            if export.channel.id != channel.id {
+                println!("Warning: merging channel '{other_name}' ({other_id}) into '{name}' ({id})", other_name = export.channel.name, other_id = export.channel.id, name = channel.name, id = channel.id);
+            }
+            let remap = export.log.authors.authors.into_iter().map(|author| authors.intern(author)).collect::<Vec<_>>();
+            messages.extend(export.log.messages.into_iter().map(|message| message.map_authors(&mut |author, _| remap[author.0])));
+        }
+
+        let len = messages.len();
+        // snowflake ids are ordered by creation time
+        messages.sort_by_key(Message::id);
+        messages.dedup_by_key(|message| message.id());
+        if messages.len() < len {
+            println!("Removed {n} duplicate messages", n = len - messages.len());
+        }
+
+        Ok(Self { channel, log: MessageLog { authors, messages } })
+    }
+}
+
+struct MessageLog {
+    authors: AuthorTable,
     messages: Vec<Message>,
 }
 
+impl<'de> Deserialize<'de> for MessageLog {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MessageLogVisitor;
+
+        impl<'de> Visitor<'de> for MessageLogVisitor {
+            type Value = MessageLog;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "an array of messages")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<MessageLog, A::Error> {
+                let mut authors = AuthorTable::default();
+                let mut messages = Vec::new();
+                while let Some(message) = seq.next_element::<Message<DeserializedAuthor>>()? {
+                    messages.push(message.map_authors(&mut |author, timestamp| authors.intern(Author::new(author, timestamp))));
+                }
+                Ok(MessageLog { authors, messages })
+            }
+        }
+
+        deserializer.deserialize_seq(MessageLogVisitor)
+    }
+}
+
+struct ProgressReader<'a, R: Read> {
+    inner: R,
+    path: &'a Path,
+    read: u64,
+    total: u64,
+    last_percent: Option<u64>,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        let percent = (self.read * 100).checked_div(self.total).unwrap_or(100);
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            print!("\rParsing '{path}'... {percent}%", path = self.path.display());
+            let _ = std::io::stdout().flush();
+        }
+        Ok(n)
+    }
+}
+
 impl TryInto<DirectMessages> for UninitDirectMessages {
     type Error = anyhow::Error;
 
     fn try_into(self) -> anyhow::Result<DirectMessages> {
-        let Self { channel, messages } = self;
+        let Self { channel, log: MessageLog { authors, messages } } = self;
 
         let mut dms = DirectMessages {
             channel,
+            authors,
             messages,
+            timezone: TimezoneSetting::Zone(Zone::Local),
+            day_start: TimeDelta::zero(),
+            window: DateRange::default(),
         };
 
         dms.init()?;
@@ -42,50 +148,213 @@ impl TryInto<DirectMessages> for UninitDirectMessages {
     }
 }
 
+#[derive(Clone)]
 pub struct DirectMessages {
     pub channel: ChannelInfo,
+    pub authors: AuthorTable,
     pub messages: Vec<Message>,
+    pub timezone: TimezoneSetting,
+    pub day_start: TimeDelta,
+    pub window: DateRange,
 }
 
 impl DirectMessages {
     fn init(&mut self) -> anyhow::Result<()> {
-        self.channel.authors = self.messages.iter().filter_map(|message| match message {
-            Message::TextMessage(text) => Some(&text.author),
-            Message::Call(call) => Some(&call.author),
-            Message::PinnedMessage(pin) => Some(&pin.author),
-            Message::AddRecipient(add) => Some(&add.author),
-            Message::RemoveRecipient(remove) => Some(&remove.author),
-            Message::Misc(_) => None
-        }).unique().map(|author| author.0.name.as_str()).collect::<Vec<_>>();
+        // authors are ordered by their first message so that series keep the same order between runs and merges
+        let order = self.messages.iter().filter_map(Message::author).chain((0..self.authors.len()).map(AuthorIdx)).unique().collect::<Vec<_>>();
+        let mut remap = vec![AuthorIdx(0); order.len()];
+        for (new, old) in order.iter().enumerate() {
+            remap[old.0] = AuthorIdx(new);
+        }
+        let mut authors = std::mem::take(&mut self.authors.authors).into_iter().map(Some).collect::<Vec<_>>();
+        self.authors = AuthorTable::default();
+        for old in order {
+            self.authors.intern(authors[old.0].take().expect("Each author is ordered once"));
+        }
+        for message in std::mem::take(&mut self.messages) {
+            self.messages.push(message.map_authors(&mut |author, _| remap[author.0]));
+        }
 
         Ok(())
     }
+
+    pub fn texts(&self) -> impl Iterator<Item=&TextMessage> + Clone {
+        self.messages.iter().filter_map(Message::as_text_message)
+    }
+
+    pub fn calls(&self) -> impl Iterator<Item=&Call> + Clone {
+        self.messages.iter().filter_map(Message::as_call)
+    }
+
+    pub fn merge_people(&mut self, people: &[Person]) {
+        let mut targets = (0..self.authors.len()).map(AuthorIdx).collect::<Vec<_>>();
+        for person in people {
+            let members = person.ids.iter().filter_map(|id| self.authors.indices.get(id).copied()).sorted().collect::<Vec<_>>();
+            let Some(&first) = members.first() else { continue };
+            for member in members {
+                targets[member.0] = first;
+            }
+            let author = &mut self.authors.authors[first.0];
+            author.label.clone_from(&person.name);
+            author.color = person.color;
+            author.timezone.clone_from(&person.timezone);
+        }
+
+        self.fold_authors(&targets.into_iter().map(Some).collect::<Vec<_>>());
+    }
+
+    pub fn zone_of(&self, author: AuthorIdx, timestamp: NaiveDateTime) -> Zone {
+        match (self.timezone, &self.authors[author].timezone) {
+            (TimezoneSetting::Zone(zone), _) => zone,
+            (TimezoneSetting::PerAuthor, Some(schedule)) => schedule.zone_at(timestamp),
+            (TimezoneSetting::PerAuthor, None) => Zone::Local,
+        }
+    }
+
+    pub fn timezone_notes(&self) -> Vec<String> {
+        let mut notes = match self.timezone {
+            TimezoneSetting::Zone(zone) => vec![format!("Times are in {zone}")],
+            TimezoneSetting::PerAuthor => self.authors.iter().map(|(_, author)| match &author.timezone {
+                Some(schedule) => format!("Times of {label} are in their own time zone: {schedule}", label = author.label),
+                None => format!("Times of {label} are in {zone}", label = author.label, zone = Zone::Local),
+            }).collect(),
+        };
+        if !self.day_start.is_zero() {
+            notes.push(format!("Days start at {time}", time = (NaiveTime::MIN + self.day_start).format("%H:%M")));
+        }
+        notes
+    }
+
+    // timestamps are kept in UTC so that differences between them stay exact, this is what hours and days are bucketed by
+    pub fn local(&self, author: AuthorIdx, timestamp: NaiveDateTime) -> NaiveDateTime {
+        self.zone_of(author, timestamp).localize(timestamp)
+    }
+
+    pub fn local_time(&self, item: &impl Timestamped) -> NaiveDateTime {
+        self.local(item.author(), item.timestamp())
+    }
+
+    // the day something happened on, days start at `day_start` rather than at midnight
+    pub fn local_date(&self, item: &impl Timestamped) -> NaiveDate {
+        (self.local_time(item) - self.day_start).date()
+    }
+
+    // the last day of the analysed window, today unless the window ends earlier
+    pub fn last_day(&self) -> NaiveDate {
+        let now = match self.timezone {
+            TimezoneSetting::Zone(zone) => zone.localize(Utc::now().naive_utc()),
+            TimezoneSetting::PerAuthor => Zone::Local.localize(Utc::now().naive_utc()),
+        };
+        let end = self.window.until.map_or(now, |until| (until - TimeDelta::milliseconds(1)).min(now));
+        (end - self.day_start).date()
+    }
+
+    pub fn retain_within(&mut self, window: DateRange) {
+        self.window = window;
+        let messages = std::mem::take(&mut self.messages);
+        self.messages = messages.into_iter().filter(|message| match (message.author(), message.timestamp()) {
+            (Some(author), Some(timestamp)) => window.contains(self.local(author, timestamp)),
+            _ => true,
+        }).collect();
+    }
+
+    pub fn filter_authors(&mut self, filter: &AuthorFilter, group_totals: bool) {
+        let excluded = self.authors.iter().filter(|(_, author)| !filter.keeps(author)).map(|(idx, _)| idx).collect::<Vec<_>>();
+        let Some(&others) = excluded.first() else { return };
+        let mut targets = (0..self.authors.len()).map(|idx| Some(AuthorIdx(idx))).collect::<Vec<_>>();
+        for idx in excluded {
+            targets[idx.0] = group_totals.then_some(others);
+        }
+        let group = self.authors[others].id;
+        self.fold_authors(&targets);
+        if group_totals {
+            // the group takes nothing from whichever excluded author it was folded into
+            let idx = self.authors.indices[&group];
+            let author = &mut self.authors.authors[idx.0];
+            author.id = GROUP_AUTHOR_ID;
+            author.label = "Everyone else".to_owned();
+            author.color = None;
+            author.timezone = None;
+            author.history.clear();
+        }
+    }
+
+    // folds each author into `targets[author]`, authors targeting themselves are kept and `None` drops the author along with their messages
+    fn fold_authors(&mut self, targets: &[Option<AuthorIdx>]) {
+        let mut authors = std::mem::take(&mut self.authors.authors).into_iter().map(Some).collect::<Vec<_>>();
+        let mut remap = vec![None; authors.len()];
+        self.authors = AuthorTable::default();
+        for (old, _) in targets.iter().enumerate().filter(|(old, target)| **target == Some(AuthorIdx(*old))) {
+            remap[old] = Some(self.authors.intern(authors[old].take().expect("Each author is kept once")));
+        }
+        for (old, target) in targets.iter().enumerate() {
+            let Some(target) = target.filter(|target| target.0 != old) else { continue };
+            let merged = authors[old].take().expect("Each author is merged once");
+            let Some(new) = remap[target.0] else { continue };
+            remap[old] = Some(new);
+            self.authors.indices.insert(merged.id, new);
+            for record in merged.history {
+                self.authors.authors[new.0].record(record);
+            }
+        }
+        for message in std::mem::take(&mut self.messages) {
+            self.messages.extend(message.map_authors(&mut |author, _| remap[author.0]).flatten_authors());
+        }
+    }
+
+    pub fn calls_at_least(&self, min_duration: TimeDelta) -> impl Iterator<Item=&Call> + Clone {
+        self.calls().filter(move |call| call.duration() >= min_duration)
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct ChannelInfo {
     #[serde(deserialize_with = "as_u64")]
     pub id: u64,
     pub name: String,
-    #[serde(default, skip)]
-    pub authors: Vec<&'static str>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 #[serde(tag = "type")]
-pub enum Message {
+pub enum Message<A = AuthorIdx> {
     #[serde(rename = "Default", alias = "Reply")]
-    TextMessage(TextMessage),
+    TextMessage(TextMessage<A>),
     #[serde(rename = "Call")]
-    Call(Call),
+    Call(Call<A>),
     #[serde(rename = "ChannelPinnedMessage")]
-    PinnedMessage(PinnedMessage),
+    PinnedMessage(PinnedMessage<A>),
     #[serde(rename = "RecipientAdd")]
-    AddRecipient(AddRecipient),
+    AddRecipient(AddRecipient<A>),
     #[serde(rename = "RecipientRemove")]
-    RemoveRecipient(RemoveRecipient),
+    RemoveRecipient(RemoveRecipient<A>),
     #[serde(rename = "35", alias = "20", alias = "23", alias = "ChannelIconChange", alias = "ChannelNameChange")]
-    Misc(Value),
+    Misc(MiscMessage),
+}
+
+impl<A> Message<A> {
+    pub fn map_authors<B>(self, f: &mut impl FnMut(A, NaiveDateTime) -> B) -> Message<B> {
+        match self {
+            Message::TextMessage(TextMessage { id, content, author, timestamp, edited_timestamp, attachments, reference }) => Message::TextMessage(TextMessage { id, content, author: f(author, timestamp), timestamp, edited_timestamp, attachments, reference }),
+            Message::Call(Call { id, start_timestamp, end_timestamp, author }) => Message::Call(Call { id, start_timestamp, end_timestamp, author: f(author, start_timestamp) }),
+            Message::PinnedMessage(PinnedMessage { id, timestamp, author, reference }) => Message::PinnedMessage(PinnedMessage { id, timestamp, author: f(author, timestamp), reference }),
+            Message::AddRecipient(AddRecipient { id, timestamp, author, added }) => Message::AddRecipient(AddRecipient { id, timestamp, author: f(author, timestamp), added: added.into_iter().map(|author| f(author, timestamp)).collect() }),
+            Message::RemoveRecipient(RemoveRecipient { id, timestamp, author, removed }) => Message::RemoveRecipient(RemoveRecipient { id, timestamp, author: f(author, timestamp), removed: removed.into_iter().map(|author| f(author, timestamp)).collect() }),
+            Message::Misc(misc) => Message::Misc(misc),
+        }
+    }
+}
+
+impl Message<Option<AuthorIdx>> {
+    fn flatten_authors(self) -> Option<Message> {
+        Some(match self {
+            Message::TextMessage(TextMessage { id, content, author, timestamp, edited_timestamp, attachments, reference }) => Message::TextMessage(TextMessage { id, content, author: author?, timestamp, edited_timestamp, attachments, reference }),
+            Message::Call(Call { id, start_timestamp, end_timestamp, author }) => Message::Call(Call { id, start_timestamp, end_timestamp, author: author? }),
+            Message::PinnedMessage(PinnedMessage { id, timestamp, author, reference }) => Message::PinnedMessage(PinnedMessage { id, timestamp, author: author?, reference }),
+            Message::AddRecipient(AddRecipient { id, timestamp, author, added }) => Message::AddRecipient(AddRecipient { id, timestamp, author: author?, added: added.into_iter().flatten().collect() }),
+            Message::RemoveRecipient(RemoveRecipient { id, timestamp, author, removed }) => Message::RemoveRecipient(RemoveRecipient { id, timestamp, author: author?, removed: removed.into_iter().flatten().collect() }),
+            Message::Misc(misc) => Message::Misc(misc),
+        })
+    }
 }
 
 impl Message {
@@ -135,7 +404,7 @@ impl Message {
     }
 
     #[inline]
-    pub fn as_misc(&self) -> Option<&Value> {
+    pub fn as_misc(&self) -> Option<&MiscMessage> {
         if let Message::Misc(inner) = self {
             Some(inner)
         } else {
@@ -156,33 +425,68 @@ impl Message {
     }
 
     #[inline]
-    pub fn author(&self) -> Option<&Author> {
+    pub fn author(&self) -> Option<AuthorIdx> {
         Some(match self {
-            Message::TextMessage(text) => &text.author,
-            Message::Call(call) => &call.author,
-            Message::PinnedMessage(pin) => &pin.author,
-            Message::AddRecipient(add) => &add.author,
-            Message::RemoveRecipient(remove) => &remove.author,
+            Message::TextMessage(text) => text.author,
+            Message::Call(call) => call.author,
+            Message::PinnedMessage(pin) => pin.author,
+            Message::AddRecipient(add) => add.author,
+            Message::RemoveRecipient(remove) => remove.author,
             Message::Misc(_) => return None
         })
     }
 
     #[inline]
-    pub fn id(&self) -> Option<u64> {
-        Some(match self {
+    pub fn id(&self) -> u64 {
+        match self {
             Message::TextMessage(text) => text.id,
             Message::Call(call) => call.id,
-            _ => return None
-        })
+            Message::PinnedMessage(pin) => pin.id,
+            Message::AddRecipient(add) => add.id,
+            Message::RemoveRecipient(remove) => remove.id,
+            Message::Misc(misc) => misc.id,
+        }
     }
 }
 
-#[derive(Deserialize)]
-pub struct TextMessage {
+#[derive(Clone, Deserialize)]
+pub struct MiscMessage {
     #[serde(deserialize_with = "as_u64")]
     pub id: u64,
-    pub content: String,
-    pub author: AuthorReference,
+}
+
+pub trait Timestamped {
+    fn author(&self) -> AuthorIdx;
+
+    fn timestamp(&self) -> NaiveDateTime;
+}
+
+impl Timestamped for TextMessage {
+    fn author(&self) -> AuthorIdx {
+        self.author
+    }
+
+    fn timestamp(&self) -> NaiveDateTime {
+        self.timestamp
+    }
+}
+
+impl Timestamped for Call {
+    fn author(&self) -> AuthorIdx {
+        self.author
+    }
+
+    fn timestamp(&self) -> NaiveDateTime {
+        self.start_timestamp
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TextMessage<A = AuthorIdx> {
+    #[serde(deserialize_with = "as_u64")]
+    pub id: u64,
+    pub content: Box<str>,
+    pub author: A,
     #[serde(deserialize_with = "timestamp_from_spec")]
     pub timestamp: NaiveDateTime,
     #[serde(deserialize_with = "opt_timestamp_from_spec", rename = "timestampEdited")]
@@ -191,55 +495,131 @@ pub struct TextMessage {
     pub reference: Option<Reference>,
 }
 
-impl TextMessage {
+impl<A> TextMessage<A> {
     pub fn content_alphanumeric_lowercase(&self) -> String {
         self.content.to_ascii_lowercase().chars().filter(|c| c.is_ascii_alphanumeric() || c.is_ascii_whitespace()).collect::<String>()
     }
 }
 
-#[derive(Eq, PartialEq, Hash)]
-pub struct AuthorReference(&'static Author);
-
-impl<'de> Deserialize<'de> for AuthorReference {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>
-    {
-        static EXISTING_AUTHORS: RwLock<FxHashMap<u64, &'static Author>> = RwLock::new(FxHashMap::with_hasher(FxBuildHasher::new()));
-
-        Ok(match DeserializedAuthor::deserialize(deserializer) {
-            Ok(author) => {
-                let DeserializedAuthor { id, nickname, name } = author;
-                let author = Author { id, nickname, name };
-                let read = EXISTING_AUTHORS.read();
-                if let Some(author) = read.get(&author.id) {
-                    Self(*author)
-                } else {
-                    drop(read);
-                    let author = Box::leak(Box::new(author));
-                    let mut write = EXISTING_AUTHORS.write();
-                    write.insert(author.id, author);
-                    Self(author)
-                }
-            },
-            Err(e) => return Err(e)
-        })
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct AuthorIdx(usize);
+
+impl AuthorIdx {
+    pub fn index(self) -> usize {
+        self.0
     }
 }
 
-impl Deref for AuthorReference {
-    type Target = Author;
+#[derive(Clone, Default)]
+pub struct AuthorTable {
+    authors: Vec<Author>,
+    indices: FxHashMap<u64, AuthorIdx>,
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl AuthorTable {
+    pub fn intern(&mut self, author: Author) -> AuthorIdx {
+        if let Some(&idx) = self.indices.get(&author.id) {
+            let existing = &mut self.authors[idx.0];
+            for record in author.history {
+                existing.record(record);
+            }
+            idx
+        } else {
+            self.authors.push(author);
+            let idx = AuthorIdx(self.authors.len() - 1);
+            self.indices.insert(self.authors[idx.0].id, idx);
+            idx
+        }
+    }
+
+    pub fn relabel(&mut self, label: AuthorLabel, aliases: &[(u64, String)]) {
+        for author in &mut self.authors {
+            author.label = match aliases.iter().find(|(id, _)| *id == author.id) {
+                Some((_, alias)) => alias.clone(),
+                None => match label {
+                    AuthorLabel::Name => author.name.clone(),
+                    AuthorLabel::Nickname if author.nickname.is_empty() => author.name.clone(),
+                    AuthorLabel::Nickname => author.nickname.clone(),
+                },
+            };
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.authors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.authors.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(AuthorIdx, &Author)> {
+        self.authors.iter().enumerate().map(|(idx, author)| (AuthorIdx(idx), author))
+    }
+
+    pub fn labels(&self) -> Vec<&str> {
+        self.authors.iter().map(|author| author.label.as_str()).collect()
+    }
+
+    pub fn colors(&self) -> Vec<Option<[u8; 3]>> {
+        self.authors.iter().map(|author| author.color).collect()
+    }
+}
+
+impl Index<AuthorIdx> for AuthorTable {
+    type Output = Author;
+
+    fn index(&self, idx: AuthorIdx) -> &Author {
+        &self.authors[idx.0]
     }
 }
 
-#[derive(Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Default, ValueEnum)]
+pub enum AuthorLabel {
+    /// The latest username
+    #[default]
+    Name,
+    /// The latest nickname, falling back to the username
+    Nickname,
+}
+
+#[derive(Clone)]
 pub struct Author {
     pub id: u64,
+    pub name: String,
     pub nickname: String,
+    pub label: String,
+    pub color: Option<[u8; 3]>,
+    pub timezone: Option<ZoneSchedule>,
+    pub history: Vec<NameRecord>,
+}
+
+impl Author {
+    fn new(DeserializedAuthor { id, nickname, name }: DeserializedAuthor, seen: NaiveDateTime) -> Self {
+        let history = vec![NameRecord { name: name.clone(), nickname: nickname.clone(), first_seen: seen, last_seen: seen }];
+        Self { id, label: name.clone(), name, nickname, color: None, timezone: None, history }
+    }
+
+    fn record(&mut self, record: NameRecord) {
+        if let Some(existing) = self.history.iter_mut().find(|existing| existing.name == record.name && existing.nickname == record.nickname) {
+            existing.first_seen = existing.first_seen.min(record.first_seen);
+            existing.last_seen = existing.last_seen.max(record.last_seen);
+        } else {
+            self.history.push(record);
+        }
+        if let Some(latest) = self.history.iter().max_by_key(|record| record.last_seen) {
+            self.name.clone_from(&latest.name);
+            self.nickname.clone_from(&latest.nickname);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NameRecord {
     pub name: String,
+    pub nickname: String,
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
 }
 
 #[derive(Deserialize)]
@@ -250,43 +630,45 @@ struct DeserializedAuthor {
     name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct Attachment {
     #[serde(deserialize_with = "as_u64")]
     pub id: u64,
-    pub url: String,
+    pub url: Box<str>,
     #[serde(rename = "fileName")]
-    pub name: String,
+    pub name: Box<str>,
     #[serde(rename = "fileSizeBytes")]
     pub size: usize,
 }
 
-#[derive(Deserialize)]
-pub struct Call {
+#[derive(Clone, Deserialize)]
+pub struct Call<A = AuthorIdx> {
     #[serde(deserialize_with = "as_u64")]
     pub id: u64,
     #[serde(rename = "timestamp", deserialize_with = "timestamp_from_spec")]
     pub start_timestamp: NaiveDateTime,
     #[serde(rename = "callEndedTimestamp", deserialize_with = "timestamp_from_spec")]
     pub end_timestamp: NaiveDateTime,
-    pub author: AuthorReference,
+    pub author: A,
 }
 
-impl Call {
+impl<A> Call<A> {
     pub fn duration(&self) -> TimeDelta {
         (self.end_timestamp - self.start_timestamp).max(TimeDelta::zero())
     }
 }
 
-#[derive(Deserialize)]
-pub struct PinnedMessage {
+#[derive(Clone, Deserialize)]
+pub struct PinnedMessage<A = AuthorIdx> {
+    #[serde(deserialize_with = "as_u64")]
+    pub id: u64,
     #[serde(deserialize_with = "timestamp_from_spec")]
     pub timestamp: NaiveDateTime,
-    pub author: AuthorReference,
+    pub author: A,
     reference: Reference,
 }
 
-impl Deref for PinnedMessage {
+impl<A> Deref for PinnedMessage<A> {
     type Target = Reference;
 
     fn deref(&self) -> &Self::Target {
@@ -294,26 +676,108 @@ impl Deref for PinnedMessage {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct Reference {
     #[serde(rename = "messageId", deserialize_with = "as_u64")]
     reference_message_id: u64,
 }
 
-#[derive(Deserialize)]
-pub struct AddRecipient {
+#[derive(Clone, Deserialize)]
+pub struct AddRecipient<A = AuthorIdx> {
+    #[serde(deserialize_with = "as_u64")]
+    pub id: u64,
     #[serde(deserialize_with = "timestamp_from_spec")]
     pub timestamp: NaiveDateTime,
-    pub author: AuthorReference,
+    pub author: A,
     #[serde(rename = "mentions")]
-    pub added: Vec<AuthorReference>,
+    pub added: Vec<A>,
 }
 
-#[derive(Deserialize)]
-pub struct RemoveRecipient {
+#[derive(Clone, Deserialize)]
+pub struct RemoveRecipient<A = AuthorIdx> {
+    #[serde(deserialize_with = "as_u64")]
+    pub id: u64,
     #[serde(deserialize_with = "timestamp_from_spec")]
     pub timestamp: NaiveDateTime,
-    pub author: AuthorReference,
+    pub author: A,
     #[serde(rename = "mentions")]
-    pub removed: Vec<AuthorReference>,
+    pub removed: Vec<A>,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // a minimal export, each message given as (id, author id, UTC timestamp)
+    pub fn export(channel: u64, messages: &[(u64, u64, &str)]) -> UninitDirectMessages {
+        let messages = messages.iter().map(|(id, author, timestamp)| serde_json::json!({
+            "id": id.to_string(),
+            "type": "Default",
+            "timestamp": format!("{timestamp}+00:00"),
+            "timestampEdited": null,
+            "content": "hi",
+            "author": { "id": author.to_string(), "name": format!("user{author}"), "nickname": format!("User {author}") },
+            "attachments": [],
+        })).collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({ "channel": { "id": channel.to_string(), "name": format!("channel{channel}") }, "messages": messages })).unwrap()
+    }
+
+    fn summary(dms: &DirectMessages) -> Vec<(u64, u64)> {
+        dms.texts().map(|text| (text.id, dms.authors[text.author].id)).collect()
+    }
+
+    #[test]
+    fn merge_sorts_and_drops_duplicates() {
+        let older = export(1, &[(10, 1, "2023-01-01T10:00:00"), (20, 2, "2023-01-01T11:00:00"), (30, 1, "2023-01-01T12:00:00")]);
+        let newer = export(1, &[(40, 3, "2023-01-01T13:00:00"), (20, 2, "2023-01-01T11:00:00"), (30, 1, "2023-01-01T12:00:00"), (50, 2, "2023-01-01T14:00:00")]);
+        let dms: DirectMessages = UninitDirectMessages::merge(vec![newer, older]).unwrap().try_into().unwrap();
+        assert_eq!(summary(&dms), [(10, 1), (20, 2), (30, 1), (40, 3), (50, 2)]);
+        assert_eq!(dms.authors.len(), 3);
+    }
+
+    #[test]
+    fn fold_authors_merges_and_drops() {
+        let mut dms: DirectMessages = export(1, &[(10, 1, "2023-01-01T10:00:00"), (20, 2, "2023-01-01T11:00:00"), (30, 3, "2023-01-01T12:00:00"), (40, 2, "2023-01-01T13:00:00")]).try_into().unwrap();
+        dms.fold_authors(&[Some(AuthorIdx(0)), Some(AuthorIdx(0)), None]);
+        assert_eq!(summary(&dms), [(10, 1), (20, 1), (40, 1)]);
+        assert_eq!(dms.authors.len(), 1);
+        assert_eq!(dms.authors.indices.get(&2).map(|idx| idx.index()), Some(0));
+        assert!(!dms.authors.indices.contains_key(&3));
+        assert_eq!(dms.authors[AuthorIdx(0)].history.iter().map(|record| record.name.as_str()).collect::<Vec<_>>(), ["user1", "user2"]);
+    }
+
+    #[test]
+    fn fold_authors_keeps_order_of_kept_authors() {
+        let mut dms: DirectMessages = export(1, &[(10, 1, "2023-01-01T10:00:00"), (20, 2, "2023-01-01T11:00:00"), (30, 3, "2023-01-01T12:00:00")]).try_into().unwrap();
+        dms.fold_authors(&[None, Some(AuthorIdx(1)), Some(AuthorIdx(2))]);
+        assert_eq!(summary(&dms), [(20, 2), (30, 3)]);
+        assert_eq!(dms.texts().map(|text| text.author.index()).collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn filter_authors_groups_without_keeping_a_real_author() {
+        let mut dms: DirectMessages = export(1, &[(10, 1, "2023-01-01T10:00:00"), (20, 2, "2023-01-01T11:00:00"), (30, 3, "2023-01-01T12:00:00")]).try_into().unwrap();
+        let berlin = ZoneSchedule::new(vec![crate::time::ZonePeriod { since: None, zone: "Europe/Berlin".parse().unwrap() }]).unwrap();
+        dms.authors.authors[1].timezone = Some(berlin);
+        dms.filter_authors(&AuthorFilter { include: vec![], exclude: vec!["2".to_owned(), "3".to_owned()] }, true);
+        assert_eq!(summary(&dms), [(10, 1), (20, GROUP_AUTHOR_ID), (30, GROUP_AUTHOR_ID)]);
+        let group = &dms.authors[AuthorIdx(1)];
+        assert_eq!(group.label, "Everyone else");
+        assert!(group.timezone.is_none());
+        assert!(group.history.is_empty());
+    }
+
+    #[test]
+    fn last_day_of_window() {
+        let mut dms: DirectMessages = export(1, &[]).try_into().unwrap();
+        dms.timezone = TimezoneSetting::Zone("+00:00".parse().unwrap());
+        let midnight = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap().and_time(NaiveTime::MIN);
+        dms.retain_within(DateRange { since: None, until: Some(midnight) });
+        assert_eq!(dms.last_day(), NaiveDate::from_ymd_opt(2023, 1, 31).unwrap());
+        dms.day_start = TimeDelta::hours(2);
+        dms.retain_within(DateRange { since: None, until: Some(midnight + dms.day_start) });
+        assert_eq!(dms.last_day(), NaiveDate::from_ymd_opt(2023, 1, 31).unwrap());
+        dms.retain_within(DateRange::default());
+        assert_eq!(dms.last_day(), (Utc::now().naive_utc() - dms.day_start).date());
+    }
 }
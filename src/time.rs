@@ -0,0 +1,182 @@
+use anyhow::{bail, Context, Result};
+use chrono::{FixedOffset, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Default)]
+pub enum Zone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Zone {
+    pub fn localize(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        // placeholders for missing timestamps stay as they are
+        if utc == NaiveDateTime::MIN || utc == NaiveDateTime::MAX {
+            return utc
+        }
+        match self {
+            Zone::Local => Local.from_utc_datetime(&utc).naive_local(),
+            Zone::Fixed(offset) => offset.from_utc_datetime(&utc).naive_local(),
+            Zone::Named(tz) => tz.from_utc_datetime(&utc).naive_local(),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local)
+        }
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Zone::Named(tz))
+        }
+        let offset = s.strip_prefix("UTC").or_else(|| s.strip_prefix("GMT")).unwrap_or(s);
+        parse_offset(offset).map(Zone::Fixed).with_context(|| format!("Expected 'local', an IANA time zone such as 'Europe/Berlin' or an offset such as '+02:00', found '{s}'"))
+    }
+}
+
+fn parse_offset(s: &str) -> Result<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'+') => (1, &s[1..]),
+        Some(b'-') => (-1, &s[1..]),
+        _ => bail!("Expected a sign"),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    let seconds = hours.parse::<i32>()? * 3600 + minutes.parse::<i32>()? * 60;
+    FixedOffset::east_opt(sign * seconds).context("Offset out of range")
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Local => write!(f, "local time ({offset})", offset = Local::now().offset()),
+            Zone::Fixed(offset) => write!(f, "UTC{offset}"),
+            Zone::Named(tz) => write!(f, "{tz}"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ZonePeriod {
+    pub since: Option<NaiveDateTime>,
+    pub zone: Zone,
+}
+
+// the zones someone lived in, each applying from its `since` (wall-clock time in that zone) until the next period starts
+#[derive(Clone)]
+pub struct ZoneSchedule {
+    periods: Vec<ZonePeriod>,
+}
+
+impl ZoneSchedule {
+    pub fn new(mut periods: Vec<ZonePeriod>) -> Result<Self> {
+        if periods.is_empty() {
+            bail!("Expected at least one time zone")
+        }
+        periods.sort_by_key(|period| period.since);
+        Ok(Self { periods })
+    }
+
+    pub fn zone_at(&self, utc: NaiveDateTime) -> Zone {
+        self.periods.iter().rev().find(|period| period.since.is_none_or(|since| since <= period.zone.localize(utc))).unwrap_or(&self.periods[0]).zone
+    }
+}
+
+impl Display for ZoneSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, period) in self.periods.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{zone}", zone = period.zone)?;
+            match (idx, period.since) {
+                (0, _) | (_, None) => {},
+                (_, Some(since)) => write!(f, " from {since}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// how far into the day it starts, e.g. `05:00` or just `5`
+pub fn parse_day_start(s: &str) -> Result<TimeDelta> {
+    let s = s.trim();
+    let time = NaiveTime::parse_from_str(s, "%H:%M").ok().or_else(|| s.parse::<u32>().ok().and_then(|hour| NaiveTime::from_hms_opt(hour, 0, 0)));
+    time.map(|time| time - NaiveTime::MIN).with_context(|| format!("Expected a time of day such as '05:00' or '5', found '{s}'"))
+}
+
+#[derive(Copy, Clone)]
+pub enum TimezoneSetting {
+    Zone(Zone),
+    PerAuthor,
+}
+
+impl FromStr for TimezoneSetting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().eq_ignore_ascii_case("per-author") {
+            Ok(TimezoneSetting::PerAuthor)
+        } else {
+            s.parse().map(TimezoneSetting::Zone)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(time.0, time.1, 0).unwrap()
+    }
+
+    #[test]
+    fn zone_at_compares_since_as_wall_clock_time() {
+        let schedule = ZoneSchedule::new(vec![
+            ZonePeriod { since: None, zone: "Asia/Tokyo".parse().unwrap() },
+            ZonePeriod { since: Some(at((2023, 1, 1), (0, 0))), zone: "Europe/Berlin".parse().unwrap() },
+        ]).unwrap();
+        // 2022-12-31 23:30 UTC is already 2023-01-01 00:30 in Berlin
+        assert!(matches!(schedule.zone_at(at((2022, 12, 31), (23, 30))), Zone::Named(chrono_tz::Europe::Berlin)));
+        assert!(matches!(schedule.zone_at(at((2022, 12, 31), (22, 30))), Zone::Named(chrono_tz::Asia::Tokyo)));
+    }
+
+    fn offset(s: &str) -> i32 {
+        match s.parse::<Zone>().unwrap() {
+            Zone::Fixed(offset) => offset.local_minus_utc(),
+            _ => panic!("'{s}' should parse as a fixed offset"),
+        }
+    }
+
+    #[test]
+    fn zone_from_str() {
+        assert!(matches!("local".parse::<Zone>().unwrap(), Zone::Local));
+        assert!(matches!(" Local ".parse::<Zone>().unwrap(), Zone::Local));
+        assert!(matches!("Europe/Berlin".parse::<Zone>().unwrap(), Zone::Named(chrono_tz::Europe::Berlin)));
+        assert_eq!(offset("+02:00"), 2 * 3600);
+        assert_eq!(offset("-0530"), -(5 * 3600 + 30 * 60));
+        assert_eq!(offset("+5"), 5 * 3600);
+        assert_eq!(offset("UTC+2"), 2 * 3600);
+        assert_eq!(offset("GMT-03:30"), -(3 * 3600 + 30 * 60));
+    }
+
+    #[test]
+    fn zone_from_str_rejects_invalid() {
+        for s in ["", "Mars/Olympus", "02:00", "+25:00", "+ab"] {
+            assert!(s.parse::<Zone>().is_err(), "'{s}' should not parse");
+        }
+    }
+}